@@ -0,0 +1,385 @@
+#![no_std]
+#![allow(clippy::too_many_arguments)]
+
+mod test;
+
+use soroban_sdk::{
+    contract, contractimpl, contractmeta, contracttype, token, Address, Env, IntoVal, TryFromVal, Val, Vec,
+};
+
+
+mod liquiditypool{
+
+    soroban_sdk::contractimport!(
+        file = "../../target/wasm32-unknown-unknown/release/soroban_liquidity_pool_contract.wasm"
+    );
+}
+
+// Scale of acc_reward_per_share, so small emissions per share don't round to 0
+const REWARD_PRECISION: i128 = 1_000_000_000_000;
+
+#[derive(Clone)]
+#[contracttype]
+pub enum DataKey {
+    Admin,
+    Pool,
+    LpToken,
+    TotalStaked,
+    Streams,
+    Staked(Address),
+    UserReward(Address, u32),
+}
+
+// A reward token emitted at "reward_per_second" between "start_time" and "end_time", shared between
+// stakers by their stake. acc_reward_per_share is the reward earned by one share since the stream started,
+// unallocated what was emitted while nothing was staked and the admin can sweep.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RewardStream {
+    pub token: Address,
+    pub reward_per_second: i128,
+    pub start_time: u64,
+    pub end_time: u64,
+    pub acc_reward_per_share: i128,
+    pub last_update: u64,
+    pub unallocated: i128,
+}
+
+// Rewards of one staker in one stream. reward_debt is what the current stake had already earned
+// when it was last settled, pending what has been settled but not claimed yet.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UserReward {
+    pub reward_debt: i128,
+    pub pending: i128,
+}
+
+
+fn get_admin(e: &Env) -> Address {
+    e.storage().instance().get(&DataKey::Admin).unwrap()
+}
+
+fn get_lp_token(e: &Env) -> Address {
+    e.storage().instance().get(&DataKey::LpToken).unwrap()
+}
+
+fn get_total_staked(e: &Env) -> i128 {
+    e.storage().instance().get(&DataKey::TotalStaked).unwrap_or(0)
+}
+
+fn get_streams(e: &Env) -> Vec<RewardStream> {
+    e.storage().instance().get(&DataKey::Streams).unwrap_or(Vec::new(e))
+}
+
+// Persistent entries stay alive for 30 days after they were last read or written
+const DAY_IN_LEDGERS: u32 = 17280;
+const PERSISTENT_BUMP_AMOUNT: u32 = 30 * DAY_IN_LEDGERS;
+const PERSISTENT_LIFETIME_THRESHOLD: u32 = PERSISTENT_BUMP_AMOUNT - DAY_IN_LEDGERS;
+
+// Reads a persistent entry, extending its lifetime when it exists
+fn get_persistent<K: IntoVal<Env, Val>, V: TryFromVal<Env, Val>>(e: &Env, key: &K) -> Option<V> {
+    let value = e.storage().persistent().get(key);
+    if value.is_some() {
+        e.storage()
+            .persistent()
+            .bump(key, PERSISTENT_LIFETIME_THRESHOLD, PERSISTENT_BUMP_AMOUNT);
+    }
+    value
+}
+
+fn put_persistent<K: IntoVal<Env, Val>, V: IntoVal<Env, Val>>(e: &Env, key: &K, value: &V) {
+    e.storage().persistent().set(key, value);
+    e.storage()
+        .persistent()
+        .bump(key, PERSISTENT_LIFETIME_THRESHOLD, PERSISTENT_BUMP_AMOUNT);
+}
+
+fn get_staked(e: &Env, user: Address) -> i128 {
+    get_persistent(e, &DataKey::Staked(user)).unwrap_or(0)
+}
+
+fn get_user_reward(e: &Env, user: Address, stream: u32) -> UserReward {
+    get_persistent(e, &DataKey::UserReward(user, stream))
+        .unwrap_or(UserReward { reward_debt: 0, pending: 0 })
+}
+
+fn put_total_staked(e: &Env, amount: i128) {
+    e.storage().instance().set(&DataKey::TotalStaked, &amount);
+}
+
+fn put_streams(e: &Env, streams: &Vec<RewardStream>) {
+    e.storage().instance().set(&DataKey::Streams, streams);
+}
+
+fn put_staked(e: &Env, user: Address, amount: i128) {
+    put_persistent(e, &DataKey::Staked(user), &amount);
+}
+
+fn put_user_reward(e: &Env, user: Address, stream: u32, reward: &UserReward) {
+    put_persistent(e, &DataKey::UserReward(user, stream), reward);
+}
+
+// Accrues the emissions since the stream's last update to acc_reward_per_share. Emissions while
+// nothing is staked go to unallocated instead.
+fn accrue(e: &Env, mut stream: RewardStream, total_staked: i128) -> RewardStream {
+    let now = e.ledger().timestamp();
+    let from = stream.last_update.max(stream.start_time);
+    let to = now.min(stream.end_time);
+
+    if to > from {
+        let reward = (to - from) as i128 * stream.reward_per_second;
+        if total_staked > 0 {
+            stream.acc_reward_per_share += reward * REWARD_PRECISION / total_staked;
+        } else {
+            stream.unallocated += reward;
+        }
+    }
+
+    stream.last_update = stream.last_update.max(now);
+    stream
+}
+
+fn update_streams(e: &Env) -> Vec<RewardStream> {
+    let total_staked = get_total_staked(e);
+    let mut streams = Vec::new(e);
+
+    for stream in get_streams(e).iter() {
+        streams.push_back(accrue(e, stream, total_staked));
+    }
+
+    put_streams(e, &streams);
+    streams
+}
+
+fn earned(staked: i128, stream: &RewardStream, reward: &UserReward) -> i128 {
+    staked * stream.acc_reward_per_share / REWARD_PRECISION - reward.reward_debt
+}
+
+// Moves what "user" earned with "staked" into pending and resets the debt for "new_staked"
+fn settle_user(e: &Env, user: &Address, streams: &Vec<RewardStream>, staked: i128, new_staked: i128) {
+    for (i, stream) in streams.iter().enumerate() {
+        let i = i as u32;
+        let mut reward = get_user_reward(e, user.clone(), i);
+
+        reward.pending += earned(staked, &stream, &reward);
+        reward.reward_debt = new_staked * stream.acc_reward_per_share / REWARD_PRECISION;
+
+        put_user_reward(e, user.clone(), i, &reward);
+    }
+}
+
+
+// Metadata that is added on to the WASM custom section
+contractmeta!(
+    key = "Farm",
+    val = "Liquidity mining rewards for LP token stakers"
+);
+
+pub trait FarmTrait {
+    // Sets the admin and the pool whose share token (share_id) is staked in this farm
+    fn initialize(e: Env, admin: Address, pool: Address);
+
+    // Adds a reward stream emitting "reward_per_second" of "reward_token" from "start_time" to "end_time".
+    // The admin funds the whole emission when adding the stream. Returns the stream index.
+    fn add_reward_stream(e: Env, reward_token: Address, reward_per_second: i128, start_time: u64, end_time: u64) -> u32;
+
+    // Changes the emission rate of "stream" from now on. The admin funds the difference over the rest of
+    // the stream when raising the rate and is refunded when lowering it. A rate of 0 pauses the stream.
+    fn set_reward_rate(e: Env, stream: u32, reward_per_second: i128);
+
+    // Pays the emissions of "stream" that accrued while nothing was staked out to the admin and returns the amount
+    fn sweep_unallocated(e: Env, stream: u32) -> i128;
+
+    // Stakes "amount" LP tokens of "user"
+    fn stake(e: Env, user: Address, amount: i128);
+
+    // Returns "amount" staked LP tokens to "user". Earned rewards stay claimable.
+    fn unstake(e: Env, user: Address, amount: i128);
+
+    // Pays out the rewards of every stream and returns the amounts claimed, by stream index
+    fn claim(e: Env, user: Address) -> Vec<i128>;
+
+    // Returns the claimable rewards of every stream at the current ledger time, by stream index
+    fn pending_rewards(e: Env, user: Address) -> Vec<i128>;
+
+    fn get_reward_streams(e: Env) -> Vec<RewardStream>;
+
+    fn get_staked(e: Env, user: Address) -> i128;
+
+    fn get_total_staked(e: Env) -> i128;
+
+    fn lp_token(e: Env) -> Address;
+}
+
+#[contract]
+struct Farm;
+
+#[contractimpl]
+impl FarmTrait for Farm {
+    fn initialize(e: Env, admin: Address, pool: Address) {
+        if e.storage().instance().has(&DataKey::Admin) {
+            panic!("Farm already initialized");
+        }
+
+        let lp_token = liquiditypool::Client::new(&e, &pool).share_id();
+
+        e.storage().instance().set(&DataKey::Admin, &admin);
+        e.storage().instance().set(&DataKey::Pool, &pool);
+        e.storage().instance().set(&DataKey::LpToken, &lp_token);
+        put_total_staked(&e, 0);
+        put_streams(&e, &Vec::new(&e));
+    }
+
+    fn add_reward_stream(e: Env, reward_token: Address, reward_per_second: i128, start_time: u64, end_time: u64) -> u32 {
+        let admin = get_admin(&e);
+        admin.require_auth();
+
+        assert!(reward_per_second > 0, "reward_per_second must be grater then 0");
+        assert!(start_time < end_time, "start_time must be before end_time");
+        assert!(start_time >= e.ledger().timestamp(), "stream can't start in the past");
+
+        let total_reward = (end_time - start_time) as i128 * reward_per_second;
+        token::Client::new(&e, &reward_token).transfer(&admin, &e.current_contract_address(), &total_reward);
+
+        let mut streams = update_streams(&e);
+        streams.push_back(RewardStream {
+            token: reward_token,
+            reward_per_second,
+            start_time,
+            end_time,
+            acc_reward_per_share: 0,
+            last_update: start_time,
+            unallocated: 0,
+        });
+        put_streams(&e, &streams);
+
+        streams.len() - 1
+    }
+
+    fn set_reward_rate(e: Env, stream: u32, reward_per_second: i128) {
+        let admin = get_admin(&e);
+        admin.require_auth();
+        assert!(reward_per_second >= 0, "reward_per_second can't be negative");
+
+        let mut streams = update_streams(&e);
+        let mut reward_stream = streams.get(stream).expect("unknown stream");
+
+        // Emissions up to now are accrued at the old rate, only the rest of the stream changes
+        let now = e.ledger().timestamp();
+        let remaining = reward_stream.end_time.saturating_sub(now.max(reward_stream.start_time)) as i128;
+        let difference = remaining * (reward_per_second - reward_stream.reward_per_second);
+
+        let reward_token = token::Client::new(&e, &reward_stream.token);
+        if difference > 0 {
+            reward_token.transfer(&admin, &e.current_contract_address(), &difference);
+        } else if difference < 0 {
+            reward_token.transfer(&e.current_contract_address(), &admin, &-difference);
+        }
+
+        reward_stream.reward_per_second = reward_per_second;
+        streams.set(stream, reward_stream);
+        put_streams(&e, &streams);
+    }
+
+    fn sweep_unallocated(e: Env, stream: u32) -> i128 {
+        let admin = get_admin(&e);
+        admin.require_auth();
+
+        let mut streams = update_streams(&e);
+        let mut reward_stream = streams.get(stream).expect("unknown stream");
+        let amount = reward_stream.unallocated;
+
+        if amount > 0 {
+            reward_stream.unallocated = 0;
+            token::Client::new(&e, &reward_stream.token).transfer(&e.current_contract_address(), &admin, &amount);
+            streams.set(stream, reward_stream);
+            put_streams(&e, &streams);
+        }
+
+        amount
+    }
+
+    fn stake(e: Env, user: Address, amount: i128) {
+        user.require_auth();
+        assert!(amount > 0, "Amount must be grater then 0");
+
+        let streams = update_streams(&e);
+        let staked = get_staked(&e, user.clone());
+        settle_user(&e, &user, &streams, staked, staked + amount);
+
+        token::Client::new(&e, &get_lp_token(&e)).transfer(&user, &e.current_contract_address(), &amount);
+
+        put_staked(&e, user, staked + amount);
+        put_total_staked(&e, get_total_staked(&e) + amount);
+    }
+
+    fn unstake(e: Env, user: Address, amount: i128) {
+        user.require_auth();
+        assert!(amount > 0, "Amount must be grater then 0");
+
+        let staked = get_staked(&e, user.clone());
+        assert!(staked >= amount, "Don't have enough staked");
+
+        let streams = update_streams(&e);
+        settle_user(&e, &user, &streams, staked, staked - amount);
+
+        put_staked(&e, user.clone(), staked - amount);
+        put_total_staked(&e, get_total_staked(&e) - amount);
+
+        token::Client::new(&e, &get_lp_token(&e)).transfer(&e.current_contract_address(), &user, &amount);
+    }
+
+    fn claim(e: Env, user: Address) -> Vec<i128> {
+        user.require_auth();
+
+        let streams = update_streams(&e);
+        let staked = get_staked(&e, user.clone());
+        settle_user(&e, &user, &streams, staked, staked);
+
+        let mut claimed = Vec::new(&e);
+        for (i, stream) in streams.iter().enumerate() {
+            let i = i as u32;
+            let mut reward = get_user_reward(&e, user.clone(), i);
+            let amount = reward.pending;
+
+            if amount > 0 {
+                reward.pending = 0;
+                put_user_reward(&e, user.clone(), i, &reward);
+                token::Client::new(&e, &stream.token).transfer(&e.current_contract_address(), &user, &amount);
+            }
+            claimed.push_back(amount);
+        }
+
+        claimed
+    }
+
+    fn pending_rewards(e: Env, user: Address) -> Vec<i128> {
+        let total_staked = get_total_staked(&e);
+        let staked = get_staked(&e, user.clone());
+
+        let mut pending = Vec::new(&e);
+        for (i, stream) in get_streams(&e).iter().enumerate() {
+            let stream = accrue(&e, stream, total_staked);
+            let reward = get_user_reward(&e, user.clone(), i as u32);
+            pending.push_back(reward.pending + earned(staked, &stream, &reward));
+        }
+
+        pending
+    }
+
+    fn get_reward_streams(e: Env) -> Vec<RewardStream> {
+        get_streams(&e)
+    }
+
+    fn get_staked(e: Env, user: Address) -> i128 {
+        get_staked(&e, user)
+    }
+
+    fn get_total_staked(e: Env) -> i128 {
+        get_total_staked(&e)
+    }
+
+    fn lp_token(e: Env) -> Address {
+        get_lp_token(&e)
+    }
+}
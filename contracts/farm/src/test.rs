@@ -0,0 +1,192 @@
+#![cfg(test)]
+extern crate std;
+
+use crate::{liquiditypool, Farm, FarmClient};
+
+use soroban_sdk::{
+    testutils::{Address as _, Ledger},
+    token, vec, Address, Env, String,
+};
+
+mod token_contract {
+    soroban_sdk::contractimport!(
+        file = "../../target/wasm32-unknown-unknown/release/soroban_token_contract.wasm"
+    );
+}
+
+fn create_token_contract<'a>(e: &Env, admin: &Address) -> token_contract::Client<'a> {
+    token_contract::Client::new(e, &e.register_stellar_asset_contract(admin.clone()))
+}
+
+fn create_liqpool_contract<'a>(e: &Env, admin: &Address, token_a: &Address, token_b: &Address) -> liquiditypool::Client<'a> {
+    let liqpool = liquiditypool::Client::new(e, &e.register_contract_wasm(None, liquiditypool::WASM));
+    liqpool.initialize(
+        &e.deployer().upload_contract_wasm(token_contract::WASM),
+        token_a,
+        token_b,
+        &Some(String::from_slice(e, "Pool Share")),
+        &Some(String::from_slice(e, "POOL")),
+        admin,
+        &None,
+    );
+    liqpool
+}
+
+#[test]
+fn test_rewards_are_shared_by_stake() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+    e.ledger().with_mut(|l| l.timestamp = 1_000);
+
+    let admin = Address::random(&e);
+    let user1 = Address::random(&e);
+    let user2 = Address::random(&e);
+
+    let token_a = create_token_contract(&e, &admin);
+    let token_b = create_token_contract(&e, &admin);
+    let reward = create_token_contract(&e, &admin);
+    let liqpool = create_liqpool_contract(&e, &admin, &token_a.address, &token_b.address);
+
+    for user in [&user1, &user2] {
+        token_a.mint(user, &10_000_000);
+        token_b.mint(user, &10_000_000);
+        liqpool.deposit(user, user, &1_000_000, &0, &1_000_000, &0);
+    }
+    reward.mint(&admin, &100_000);
+
+    let farm = FarmClient::new(&e, &e.register_contract(None, Farm {}));
+    farm.initialize(&admin, &liqpool.address);
+    assert_eq!(farm.lp_token(), liqpool.share_id());
+
+    // 100 per second for 1000 seconds
+    assert_eq!(farm.add_reward_stream(&reward.address, &100, &1_000, &2_000), 0);
+    assert_eq!(reward.balance(&farm.address), 100_000);
+
+    let lp_token = token::Client::new(&e, &farm.lp_token());
+    farm.stake(&user1, &500_000);
+    assert_eq!(farm.get_staked(&user1), 500_000);
+
+    // user1 alone for 500 seconds
+    e.ledger().with_mut(|l| l.timestamp = 1_500);
+    assert_eq!(farm.pending_rewards(&user1), vec![&e, 50_000]);
+
+    // user1 and user2 with equal stakes until the stream ends
+    farm.stake(&user2, &500_000);
+    e.ledger().with_mut(|l| l.timestamp = 3_000);
+
+    assert_eq!(farm.claim(&user1), vec![&e, 75_000]);
+    assert_eq!(farm.claim(&user2), vec![&e, 25_000]);
+    assert_eq!(reward.balance(&user1), 75_000);
+    assert_eq!(reward.balance(&user2), 25_000);
+    assert_eq!(farm.pending_rewards(&user1), vec![&e, 0]);
+
+    let shares1 = lp_token.balance(&user1);
+    farm.unstake(&user1, &500_000);
+    assert_eq!(lp_token.balance(&user1), shares1 + 500_000);
+    assert_eq!(farm.get_total_staked(), 500_000);
+}
+
+// Farm over a fresh pool that each of "stakers" deposited 1_000_000 of both tokens into, with 1_000_000 reward tokens minted to "admin"
+fn setup_farm<'a>(e: &Env, admin: &Address, stakers: &[&Address]) -> (FarmClient<'a>, token_contract::Client<'a>) {
+    let token_a = create_token_contract(e, admin);
+    let token_b = create_token_contract(e, admin);
+    let reward = create_token_contract(e, admin);
+    let liqpool = create_liqpool_contract(e, admin, &token_a.address, &token_b.address);
+
+    for user in stakers {
+        token_a.mint(user, &10_000_000);
+        token_b.mint(user, &10_000_000);
+        liqpool.deposit(user, user, &1_000_000, &0, &1_000_000, &0);
+    }
+    reward.mint(admin, &1_000_000);
+
+    let farm = FarmClient::new(e, &e.register_contract(None, Farm {}));
+    farm.initialize(admin, &liqpool.address);
+
+    (farm, reward)
+}
+
+#[test]
+fn test_claim_before_start_and_after_end() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+    e.ledger().with_mut(|l| l.timestamp = 1_000);
+
+    let admin = Address::random(&e);
+    let user = Address::random(&e);
+    let (farm, reward) = setup_farm(&e, &admin, &[&user]);
+
+    farm.add_reward_stream(&reward.address, &100, &2_000, &3_000);
+    farm.stake(&user, &500_000);
+
+    // Nothing is emitted before the stream starts
+    e.ledger().with_mut(|l| l.timestamp = 1_500);
+    assert_eq!(farm.claim(&user), vec![&e, 0]);
+    assert_eq!(reward.balance(&user), 0);
+
+    // Nor after it ends
+    e.ledger().with_mut(|l| l.timestamp = 5_000);
+    assert_eq!(farm.claim(&user), vec![&e, 100_000]);
+    assert_eq!(farm.claim(&user), vec![&e, 0]);
+    assert_eq!(reward.balance(&user), 100_000);
+    assert_eq!(reward.balance(&farm.address), 0);
+}
+
+#[test]
+fn test_emissions_without_stakers_can_be_swept() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+    e.ledger().with_mut(|l| l.timestamp = 1_000);
+
+    let admin = Address::random(&e);
+    let user = Address::random(&e);
+    let (farm, reward) = setup_farm(&e, &admin, &[&user]);
+
+    farm.add_reward_stream(&reward.address, &100, &1_000, &2_000);
+
+    // Nobody staked for the first 400 seconds
+    e.ledger().with_mut(|l| l.timestamp = 1_400);
+    assert_eq!(farm.claim(&user), vec![&e, 0]);
+    farm.stake(&user, &500_000);
+    assert_eq!(farm.get_reward_streams().get(0).unwrap().unallocated, 40_000);
+
+    e.ledger().with_mut(|l| l.timestamp = 2_000);
+    assert_eq!(farm.claim(&user), vec![&e, 60_000]);
+
+    assert_eq!(farm.sweep_unallocated(&0), 40_000);
+    assert_eq!(farm.sweep_unallocated(&0), 0);
+    assert_eq!(reward.balance(&admin), 1_000_000 - 100_000 + 40_000);
+    assert_eq!(reward.balance(&farm.address), 0);
+}
+
+#[test]
+fn test_reward_rate_changes() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+    e.ledger().with_mut(|l| l.timestamp = 1_000);
+
+    let admin = Address::random(&e);
+    let user = Address::random(&e);
+    let (farm, reward) = setup_farm(&e, &admin, &[&user]);
+
+    farm.add_reward_stream(&reward.address, &100, &1_000, &2_000);
+    farm.stake(&user, &500_000);
+
+    // Doubling the rate halfway funds another 100 per second for the remaining 500 seconds
+    e.ledger().with_mut(|l| l.timestamp = 1_500);
+    farm.set_reward_rate(&0, &200);
+    assert_eq!(reward.balance(&admin), 1_000_000 - 100_000 - 50_000);
+
+    // Pausing refunds what the last 250 seconds would have emitted
+    e.ledger().with_mut(|l| l.timestamp = 1_750);
+    farm.set_reward_rate(&0, &0);
+    assert_eq!(reward.balance(&admin), 1_000_000 - 150_000 + 50_000);
+
+    e.ledger().with_mut(|l| l.timestamp = 2_000);
+    assert_eq!(farm.claim(&user), vec![&e, 50_000 + 50_000]);
+    assert_eq!(reward.balance(&farm.address), 0);
+}
@@ -0,0 +1,324 @@
+#![no_std]
+#![allow(clippy::too_many_arguments)]
+
+mod test;
+mod lptoken;
+mod math;
+
+
+use num_integer::Roots;
+use soroban_sdk::{
+    contract, contractimpl, contractmeta, contracttype, token, Address, BytesN, Env, String,
+};
+use lptoken::create_contract;
+use math::{mul_div, pow_ratio, SCALE};
+
+// Weights are in basis points of the pool, weight_b is always 10000 - weight_a
+const WEIGHT_DENOMINATOR: u32 = 10000;
+const MIN_WEIGHT_BPS: u32 = 100;
+
+const SWAP_FEE_BPS: i128 = 25;
+
+// pow_ratio is rounded up by this much, so fixed point errors never pay out more than the exact output
+const POW_ROUNDING: i128 = 1_000_000;
+
+#[derive(Clone)]
+#[contracttype]
+pub enum DataKey {
+    Owner,
+    TokenA,
+    TokenB,
+    TokenShare,
+    TotalShares,
+    ReserveA,
+    ReserveB,
+    Schedule,
+}
+
+// token_a's weight moves linearly from start_weight_a to end_weight_a between start_time and end_time
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WeightSchedule {
+    pub start_weight_a: u32,
+    pub end_weight_a: u32,
+    pub start_time: u64,
+    pub end_time: u64,
+}
+
+
+fn get_owner(e: &Env) -> Address {
+    e.storage().instance().get(&DataKey::Owner).unwrap()
+}
+
+fn get_token_a(e: &Env) -> Address {
+    e.storage().instance().get(&DataKey::TokenA).unwrap()
+}
+
+fn get_token_b(e: &Env) -> Address {
+    e.storage().instance().get(&DataKey::TokenB).unwrap()
+}
+
+fn get_token_share(e: &Env) -> Address {
+    e.storage().instance().get(&DataKey::TokenShare).unwrap()
+}
+
+fn get_total_shares(e: &Env) -> i128 {
+    e.storage().instance().get(&DataKey::TotalShares).unwrap()
+}
+
+fn get_reserve_a(e: &Env) -> i128 {
+    e.storage().instance().get(&DataKey::ReserveA).unwrap()
+}
+
+fn get_reserve_b(e: &Env) -> i128 {
+    e.storage().instance().get(&DataKey::ReserveB).unwrap()
+}
+
+fn get_schedule(e: &Env) -> WeightSchedule {
+    e.storage().instance().get(&DataKey::Schedule).unwrap()
+}
+
+fn put_total_shares(e: &Env, amount: i128) {
+    e.storage().instance().set(&DataKey::TotalShares, &amount)
+}
+
+fn put_reserve_a(e: &Env, amount: i128) {
+    e.storage().instance().set(&DataKey::ReserveA, &amount)
+}
+
+fn put_reserve_b(e: &Env, amount: i128) {
+    e.storage().instance().set(&DataKey::ReserveB, &amount)
+}
+
+fn mint_shares(e: &Env, to: Address, amount: i128) {
+    lptoken::Client::new(e, &get_token_share(e)).mint(&to, &amount);
+    put_total_shares(e, get_total_shares(e) + amount);
+}
+
+fn burn_shares(e: &Env, from: Address, amount: i128) {
+    lptoken::Client::new(e, &get_token_share(e)).burn(&from, &amount);
+    put_total_shares(e, get_total_shares(e) - amount);
+}
+
+// Current (weight_a, weight_b) in basis points
+fn get_weights(e: &Env) -> (u32, u32) {
+    let schedule = get_schedule(e);
+    let now = e.ledger().timestamp();
+
+    let weight_a = if now <= schedule.start_time {
+        schedule.start_weight_a
+    } else if now >= schedule.end_time {
+        schedule.end_weight_a
+    } else {
+        let elapsed = (now - schedule.start_time) as i128;
+        let duration = (schedule.end_time - schedule.start_time) as i128;
+        let start = schedule.start_weight_a as i128;
+        let end = schedule.end_weight_a as i128;
+        (start + (end - start) * elapsed / duration) as u32
+    };
+
+    (weight_a, WEIGHT_DENOMINATOR - weight_a)
+}
+
+// Weighted constant product output: reserve_out * (1 - (reserve_in / (reserve_in + amount_in_after_fee))^(weight_in / weight_out))
+pub fn get_amount_out(amount_in: i128, reserve_in: i128, reserve_out: i128, weight_in: u32, weight_out: u32) -> i128 {
+    assert!(amount_in > 0, "Amount must be grater then 0");
+    assert!(reserve_in > 0 && reserve_out > 0, "ERROR_INSUFFICIENT_LIQUIDITY");
+
+    let amount_in_with_fee = amount_in * (10000 - SWAP_FEE_BPS) / 10000;
+    let base = mul_div(reserve_in, SCALE, reserve_in + amount_in_with_fee);
+    let power = (pow_ratio(base, weight_in as i128, weight_out as i128) + POW_ROUNDING).min(SCALE);
+
+    mul_div(reserve_out, SCALE - power, SCALE)
+}
+
+
+// Metadata that is added on to the WASM custom section
+contractmeta!(
+    key = "LBP",
+    val = "Liquidity bootstrapping pool with time-varying weights"
+);
+
+pub trait LbpTrait {
+    // Sets the tokens, the owner and the weight schedule of the pool and deploys its share token.
+    // Weights are token_a's share of the pool in basis points, between 100 and 9900.
+    fn initialize(e: Env, token_wasm_hash: BytesN<32>, owner: Address, token_a: Address, token_b: Address, start_weight_a: u32, end_weight_a: u32, start_time: u64, end_time: u64);
+
+    // Returns the token contract address for the pool share token
+    fn share_id(e: Env) -> Address;
+
+    // Seeds the pool with "amount_a" and "amount_b" from the owner before the sale starts and mints the
+    // pool shares to the owner. Can only be done once.
+    fn fund(e: Env, amount_a: i128, amount_b: i128) -> i128;
+
+    // Sells "amount_in" of token_a if "sell_a" is true, else of token_b, at the current weights. Only open
+    // between start_time and end_time.
+    fn swap_exact_input(e: Env, from: Address, to: Address, sell_a: bool, amount_in: i128, min_out: i128) -> i128;
+
+    // Burns "share_amount" shares of the owner after end_time and sends the underlying reserves to "to"
+    fn withdraw(e: Env, to: Address, share_amount: i128) -> (i128, i128);
+
+    // Returns the output of selling "amount_in" at the current weights and reserves
+    fn get_amount_out(e: Env, sell_a: bool, amount_in: i128) -> i128;
+
+    // Returns the current (weight_a, weight_b) in basis points
+    fn get_weights(e: Env) -> (u32, u32);
+
+    fn get_schedule(e: Env) -> WeightSchedule;
+
+    fn get_rsrvs(e: Env) -> (i128, i128);
+
+    // Returns the token_a and token_b contract addresses of this pool
+    fn get_tokens(e: Env) -> (Address, Address);
+}
+
+#[contract]
+struct Lbp;
+
+#[contractimpl]
+impl LbpTrait for Lbp {
+    fn initialize(e: Env, token_wasm_hash: BytesN<32>, owner: Address, token_a: Address, token_b: Address, start_weight_a: u32, end_weight_a: u32, start_time: u64, end_time: u64) {
+        if e.storage().instance().has(&DataKey::Owner) {
+            panic!("Pool already initialized");
+        }
+
+        for weight in [start_weight_a, end_weight_a] {
+            assert!(
+                (MIN_WEIGHT_BPS..=WEIGHT_DENOMINATOR - MIN_WEIGHT_BPS).contains(&weight),
+                "weight out of range"
+            );
+        }
+        assert!(start_time < end_time, "start_time must be before end_time");
+
+        let share_contract = create_contract(&e, token_wasm_hash, &token_a, &token_b);
+        lptoken::Client::new(&e, &share_contract).initialize(
+            &e.current_contract_address(),
+            &8u32,
+            &String::from_slice(&e, "SorobanSwap LBP Share"),
+            &String::from_slice(&e, "SSLBP"),
+        );
+
+        e.storage().instance().set(&DataKey::Owner, &owner);
+        e.storage().instance().set(&DataKey::TokenA, &token_a);
+        e.storage().instance().set(&DataKey::TokenB, &token_b);
+        e.storage().instance().set(&DataKey::TokenShare, &share_contract);
+        e.storage().instance().set(&DataKey::Schedule, &WeightSchedule {
+            start_weight_a,
+            end_weight_a,
+            start_time,
+            end_time,
+        });
+        put_total_shares(&e, 0);
+        put_reserve_a(&e, 0);
+        put_reserve_b(&e, 0);
+    }
+
+    fn share_id(e: Env) -> Address {
+        get_token_share(&e)
+    }
+
+    fn fund(e: Env, amount_a: i128, amount_b: i128) -> i128 {
+        let owner = get_owner(&e);
+        owner.require_auth();
+
+        assert!(get_total_shares(&e) == 0, "Pool already funded");
+        assert!(e.ledger().timestamp() < get_schedule(&e).start_time, "Sale already started");
+        assert!(amount_a > 0 && amount_b > 0, "Amount must be grater then 0");
+
+        token::Client::new(&e, &get_token_a(&e)).transfer(&owner, &e.current_contract_address(), &amount_a);
+        token::Client::new(&e, &get_token_b(&e)).transfer(&owner, &e.current_contract_address(), &amount_b);
+
+        // The product overflows for large reserves, the product of the roots is close enough for the initial supply
+        let shares = match amount_a.checked_mul(amount_b) {
+            Some(product) => product.sqrt(),
+            None => amount_a.sqrt() * amount_b.sqrt(),
+        };
+        mint_shares(&e, owner, shares);
+        put_reserve_a(&e, amount_a);
+        put_reserve_b(&e, amount_b);
+
+        shares
+    }
+
+    fn swap_exact_input(e: Env, from: Address, to: Address, sell_a: bool, amount_in: i128, min_out: i128) -> i128 {
+        from.require_auth();
+
+        let schedule = get_schedule(&e);
+        let now = e.ledger().timestamp();
+        assert!(now >= schedule.start_time && now < schedule.end_time, "Sale is not active");
+
+        let (reserve_a, reserve_b) = (get_reserve_a(&e), get_reserve_b(&e));
+        let (weight_a, weight_b) = get_weights(&e);
+        let (sell_token, buy_token, amount_out) = if sell_a {
+            (get_token_a(&e), get_token_b(&e), get_amount_out(amount_in, reserve_a, reserve_b, weight_a, weight_b))
+        } else {
+            (get_token_b(&e), get_token_a(&e), get_amount_out(amount_in, reserve_b, reserve_a, weight_b, weight_a))
+        };
+
+        if amount_out < min_out {
+            panic!("Not satisfied minimum out")
+        }
+
+        token::Client::new(&e, &sell_token).transfer(&from, &e.current_contract_address(), &amount_in);
+        token::Client::new(&e, &buy_token).transfer(&e.current_contract_address(), &to, &amount_out);
+
+        if sell_a {
+            put_reserve_a(&e, reserve_a + amount_in);
+            put_reserve_b(&e, reserve_b - amount_out);
+        } else {
+            put_reserve_a(&e, reserve_a - amount_out);
+            put_reserve_b(&e, reserve_b + amount_in);
+        }
+
+        amount_out
+    }
+
+    fn withdraw(e: Env, to: Address, share_amount: i128) -> (i128, i128) {
+        let owner = get_owner(&e);
+        owner.require_auth();
+
+        assert!(e.ledger().timestamp() >= get_schedule(&e).end_time, "Sale has not ended");
+        assert!(share_amount > 0, "Amount must be grater then 0");
+
+        let total_shares = get_total_shares(&e);
+        let (reserve_a, reserve_b) = (get_reserve_a(&e), get_reserve_b(&e));
+        let amount_a = mul_div(reserve_a, share_amount, total_shares);
+        let amount_b = mul_div(reserve_b, share_amount, total_shares);
+
+        burn_shares(&e, owner, share_amount);
+        token::Client::new(&e, &get_token_a(&e)).transfer(&e.current_contract_address(), &to, &amount_a);
+        token::Client::new(&e, &get_token_b(&e)).transfer(&e.current_contract_address(), &to, &amount_b);
+
+        put_reserve_a(&e, reserve_a - amount_a);
+        put_reserve_b(&e, reserve_b - amount_b);
+
+        (amount_a, amount_b)
+    }
+
+    fn get_amount_out(e: Env, sell_a: bool, amount_in: i128) -> i128 {
+        let (reserve_a, reserve_b) = (get_reserve_a(&e), get_reserve_b(&e));
+        let (weight_a, weight_b) = get_weights(&e);
+
+        if sell_a {
+            get_amount_out(amount_in, reserve_a, reserve_b, weight_a, weight_b)
+        } else {
+            get_amount_out(amount_in, reserve_b, reserve_a, weight_b, weight_a)
+        }
+    }
+
+    fn get_weights(e: Env) -> (u32, u32) {
+        get_weights(&e)
+    }
+
+    fn get_schedule(e: Env) -> WeightSchedule {
+        get_schedule(&e)
+    }
+
+    fn get_rsrvs(e: Env) -> (i128, i128) {
+        (get_reserve_a(&e), get_reserve_b(&e))
+    }
+
+    fn get_tokens(e: Env) -> (Address, Address) {
+        (get_token_a(&e), get_token_b(&e))
+    }
+}
@@ -0,0 +1,102 @@
+// Fixed point math with 18 decimals for weighted pool pricing
+
+pub const SCALE: i128 = 1_000_000_000_000_000_000;
+
+const LN_2: i128 = 693_147_180_559_945_309;
+
+// Natural logarithm of "x" / SCALE, scaled by SCALE
+pub fn ln(x: i128) -> i128 {
+    assert!(x > 0, "ln of non positive value");
+
+    // x = m * 2^k with m in [1, 2)
+    let mut m = x;
+    let mut k = 0i128;
+    while m >= 2 * SCALE {
+        m /= 2;
+        k += 1;
+    }
+    while m < SCALE {
+        m *= 2;
+        k -= 1;
+    }
+
+    // ln(m) = 2 * (z + z^3/3 + z^5/5 + ...) with z = (m - 1) / (m + 1), which is at most 1/3
+    let z = (m - SCALE) * SCALE / (m + SCALE);
+    let z2 = z * z / SCALE;
+    let mut term = z;
+    let mut sum = 0;
+    let mut n = 1;
+    while term != 0 {
+        sum += term / n;
+        term = term * z2 / SCALE;
+        n += 2;
+    }
+
+    2 * sum + k * LN_2
+}
+
+// e^("y" / SCALE), scaled by SCALE. Only used for y <= 0, so the result never exceeds SCALE.
+pub fn exp(y: i128) -> i128 {
+    assert!(y <= 0, "exp of positive value");
+
+    // y = k * ln(2) + r with r in [0, ln(2))
+    let k = y.div_euclid(LN_2);
+    let r = y.rem_euclid(LN_2);
+    if k <= -127 {
+        return 0;
+    }
+
+    let mut term = SCALE;
+    let mut sum = SCALE;
+    let mut n = 1;
+    while term != 0 {
+        term = term * r / SCALE / n;
+        sum += term;
+        n += 1;
+    }
+
+    sum >> (-k) as u32
+}
+
+// ("base" / SCALE)^("weight_num" / "weight_den"), scaled by SCALE, for base in (0, SCALE]
+pub fn pow_ratio(base: i128, weight_num: i128, weight_den: i128) -> i128 {
+    exp(ln(base) * weight_num / weight_den)
+}
+
+// "a" * "b" / "c" for non negative "a", "b" and positive "c", rounded down. The product is kept in 256 bits,
+// so it doesn't overflow for reserves above i128::MAX / SCALE. Panics if the result doesn't fit in i128.
+pub fn mul_div(a: i128, b: i128, c: i128) -> i128 {
+    assert!(a >= 0 && b >= 0 && c > 0, "mul_div of negative value");
+    let (a, b, c) = (a as u128, b as u128, c as u128);
+
+    // 128 x 128 bit product as hi * 2^128 + lo, from 64 bit halves
+    let mask = u64::MAX as u128;
+    let (a_hi, a_lo) = (a >> 64, a & mask);
+    let (b_hi, b_lo) = (b >> 64, b & mask);
+
+    let lo_lo = a_lo * b_lo;
+    let cross = (lo_lo >> 64) + ((a_hi * b_lo) & mask) + a_lo * b_hi;
+    let lo = (cross << 64) | (lo_lo & mask);
+    let hi = a_hi * b_hi + ((a_hi * b_lo) >> 64) + (cross >> 64);
+
+    if hi == 0 {
+        return (lo / c) as i128;
+    }
+    assert!(hi < c, "mul_div overflow");
+
+    // Long division, hi < c keeps the quotient within 128 bits
+    let mut rem = hi;
+    let mut quotient = 0u128;
+    for i in (0..128).rev() {
+        let carry = rem >> 127;
+        rem = (rem << 1) | ((lo >> i) & 1);
+        quotient <<= 1;
+        if carry == 1 || rem >= c {
+            rem = rem.wrapping_sub(c);
+            quotient |= 1;
+        }
+    }
+
+    assert!(quotient <= i128::MAX as u128, "mul_div overflow");
+    quotient as i128
+}
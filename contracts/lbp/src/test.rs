@@ -0,0 +1,125 @@
+#![cfg(test)]
+extern crate std;
+
+use crate::{get_amount_out, lptoken, math, Lbp, LbpClient};
+
+use soroban_sdk::{
+    testutils::{Address as _, Ledger},
+    Address, Env,
+};
+
+fn create_token_contract<'a>(e: &Env, admin: &Address) -> lptoken::Client<'a> {
+    lptoken::Client::new(e, &e.register_stellar_asset_contract(admin.clone()))
+}
+
+#[test]
+fn test_fixed_point_math() {
+    assert_eq!(math::ln(math::SCALE), 0);
+    assert!((math::ln(math::SCALE / 2) + 693_147_180_559_945_309).abs() < 1_000);
+    assert!((math::exp(-693_147_180_559_945_309) - math::SCALE / 2).abs() < 1_000);
+
+    // 0.25^(1/2)
+    assert!((math::pow_ratio(math::SCALE / 4, 1, 2) - math::SCALE / 2).abs() < 1_000);
+}
+
+#[test]
+fn test_equal_weights_match_constant_product() {
+    let amount_out = get_amount_out(10_000, 1_000_000, 1_000_000, 5000, 5000);
+
+    // 1_000_000 * 9_975 / (1_000_000 + 9_975)
+    assert_eq!(amount_out, 9_876);
+}
+
+#[test]
+fn test_mul_div_keeps_wide_products() {
+    assert_eq!(math::mul_div(7, 6, 4), 10);
+    assert_eq!(math::mul_div(i128::MAX, 3, 3), i128::MAX);
+    assert_eq!(math::mul_div(10i128.pow(24), math::SCALE, 10i128.pow(24) + 10i128.pow(22)), math::SCALE * 100 / 101);
+}
+
+#[test]
+fn test_large_reserves_do_not_overflow() {
+    // reserve * SCALE overflows i128 from about 1.7e20
+    let reserve = 10i128.pow(24);
+    let amount_in = 10i128.pow(22);
+    let amount_out = get_amount_out(amount_in, reserve, reserve, 5000, 5000);
+
+    // Close to the constant product output, and never above it
+    let amount_in_with_fee = amount_in * 9_975 / 10_000;
+    let expected = math::mul_div(reserve, amount_in_with_fee, reserve + amount_in_with_fee);
+    assert!(amount_out <= expected && expected - amount_out < expected / 1_000_000);
+
+    let e = Env::default();
+    e.mock_all_auths();
+    e.ledger().with_mut(|l| l.timestamp = 1_000);
+
+    let admin = Address::random(&e);
+    let owner = Address::random(&e);
+    let buyer = Address::random(&e);
+
+    let project = create_token_contract(&e, &admin);
+    let usdc = create_token_contract(&e, &admin);
+    project.mint(&owner, &reserve);
+    usdc.mint(&owner, &reserve);
+    usdc.mint(&buyer, &amount_in);
+
+    let lbp = LbpClient::new(&e, &e.register_contract(None, Lbp {}));
+    lbp.initialize(&e.deployer().upload_contract_wasm(lptoken::WASM), &owner, &project.address, &usdc.address, &5000, &5000, &2_000, &4_000);
+    let shares = lbp.fund(&reserve, &reserve);
+
+    e.ledger().with_mut(|l| l.timestamp = 3_000);
+    let bought = lbp.swap_exact_input(&buyer, &buyer, &false, &amount_in, &amount_out);
+    assert_eq!(bought, amount_out);
+
+    e.ledger().with_mut(|l| l.timestamp = 4_000);
+    assert_eq!(lbp.withdraw(&owner, &shares), (reserve - bought, reserve + amount_in));
+}
+
+#[test]
+fn test_weights_shift_over_sale() {
+    let e = Env::default();
+    e.mock_all_auths();
+    e.ledger().with_mut(|l| l.timestamp = 1_000);
+
+    let admin = Address::random(&e);
+    let owner = Address::random(&e);
+    let buyer = Address::random(&e);
+
+    let project = create_token_contract(&e, &admin);
+    let usdc = create_token_contract(&e, &admin);
+    project.mint(&owner, &1_000_000);
+    usdc.mint(&owner, &100_000);
+    usdc.mint(&buyer, &100_000);
+
+    let lbp = LbpClient::new(&e, &e.register_contract(None, Lbp {}));
+    lbp.initialize(
+        &e.deployer().upload_contract_wasm(lptoken::WASM),
+        &owner,
+        &project.address,
+        &usdc.address,
+        &9000,
+        &5000,
+        &2_000,
+        &4_000,
+    );
+    lbp.fund(&1_000_000, &100_000);
+
+    assert_eq!(lbp.get_weights(), (9000, 1000));
+    e.ledger().with_mut(|l| l.timestamp = 3_000);
+    assert_eq!(lbp.get_weights(), (7000, 3000));
+
+    // The project token gets cheaper as its weight falls
+    let early = lbp.get_amount_out(&false, &1_000);
+    e.ledger().with_mut(|l| l.timestamp = 3_900);
+    let late = lbp.get_amount_out(&false, &1_000);
+    assert!(late > early);
+
+    let bought = lbp.swap_exact_input(&buyer, &buyer, &false, &1_000, &late);
+    assert_eq!(project.balance(&buyer), bought);
+    assert_eq!(lbp.get_rsrvs(), (1_000_000 - bought, 101_000));
+
+    e.ledger().with_mut(|l| l.timestamp = 4_000);
+    let shares = lptoken::Client::new(&e, &lbp.share_id()).balance(&owner);
+    assert_eq!(lbp.withdraw(&owner, &shares), (1_000_000 - bought, 101_000));
+    assert_eq!(lbp.get_rsrvs(), (0, 0));
+}
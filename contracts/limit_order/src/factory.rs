@@ -0,0 +1,9 @@
+use soroban_sdk::{contractclient, Address, Env};
+
+// The part of the router's interface orders need. The router deploys the pool of every pair, so a pool
+// it returns for the order's tokens is known to be a SorobanSwap pool.
+#[allow(dead_code)]
+#[contractclient(name = "FactoryClient")]
+pub trait Factory {
+    fn get_pool(e: Env, token_a: Address, token_b: Address) -> Option<Address>;
+}
@@ -0,0 +1,261 @@
+#![no_std]
+#![allow(clippy::too_many_arguments)]
+
+mod test;
+mod factory;
+
+use soroban_sdk::{
+    auth::{ContractContext, InvokerContractAuthEntry, SubContractInvocation},
+    contract, contractimpl, contractmeta, contracttype, token, vec, Address, Env, IntoVal, Symbol, TryFromVal, Val, Vec,
+};
+use factory::FactoryClient;
+
+
+mod liquiditypool{
+
+    soroban_sdk::contractimport!(
+        file = "../../target/wasm32-unknown-unknown/release/soroban_liquidity_pool_contract.wasm"
+    );
+}
+
+#[derive(Clone)]
+#[contracttype]
+pub enum DataKey {
+    Factory,
+    OrderCount,
+    Order(u32),
+    OwnerOrders(Address),
+}
+
+// Sells "amount_in" of "sell_token" for at least "min_out" of "buy_token" through "pool", where sell_a
+// tells whether sell_token is the pool's token_a. The tokens are fixed when the order is placed.
+// The contract escrows amount_in plus "tip", which is paid in the sold token to whoever executes the order.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Order {
+    pub owner: Address,
+    pub pool: Address,
+    pub sell_token: Address,
+    pub buy_token: Address,
+    pub sell_a: bool,
+    pub amount_in: i128,
+    pub min_out: i128,
+    pub tip: i128,
+    pub expiration: u64,
+}
+
+
+fn get_factory(e: &Env) -> Address {
+    e.storage().instance().get(&DataKey::Factory).unwrap()
+}
+
+fn get_order_count(e: &Env) -> u32 {
+    e.storage().instance().get(&DataKey::OrderCount).unwrap_or(0)
+}
+
+// Persistent entries stay alive for 30 days after they were last read or written
+const DAY_IN_LEDGERS: u32 = 17280;
+const PERSISTENT_BUMP_AMOUNT: u32 = 30 * DAY_IN_LEDGERS;
+const PERSISTENT_LIFETIME_THRESHOLD: u32 = PERSISTENT_BUMP_AMOUNT - DAY_IN_LEDGERS;
+
+// Reads a persistent entry, extending its lifetime when it exists
+fn get_persistent<K: IntoVal<Env, Val>, V: TryFromVal<Env, Val>>(e: &Env, key: &K) -> Option<V> {
+    let value = e.storage().persistent().get(key);
+    if value.is_some() {
+        e.storage()
+            .persistent()
+            .bump(key, PERSISTENT_LIFETIME_THRESHOLD, PERSISTENT_BUMP_AMOUNT);
+    }
+    value
+}
+
+fn put_persistent<K: IntoVal<Env, Val>, V: IntoVal<Env, Val>>(e: &Env, key: &K, value: &V) {
+    e.storage().persistent().set(key, value);
+    e.storage()
+        .persistent()
+        .bump(key, PERSISTENT_LIFETIME_THRESHOLD, PERSISTENT_BUMP_AMOUNT);
+}
+
+fn get_order(e: &Env, order_id: u32) -> Order {
+    get_persistent(e, &DataKey::Order(order_id))
+        .unwrap_or_else(|| panic!("Order not found"))
+}
+
+fn get_owner_orders(e: &Env, owner: Address) -> Vec<u32> {
+    get_persistent(e, &DataKey::OwnerOrders(owner)).unwrap_or(Vec::new(e))
+}
+
+fn put_order_count(e: &Env, count: u32) {
+    e.storage().instance().set(&DataKey::OrderCount, &count);
+}
+
+fn put_order(e: &Env, order_id: u32, order: &Order) {
+    put_persistent(e, &DataKey::Order(order_id), order);
+}
+
+fn put_owner_orders(e: &Env, owner: Address, orders: &Vec<u32>) {
+    put_persistent(e, &DataKey::OwnerOrders(owner), orders);
+}
+
+// Drops a filled or cancelled order from storage and from its owner's open orders
+fn remove_order(e: &Env, order_id: u32, order: &Order) {
+    e.storage().persistent().remove(&DataKey::Order(order_id));
+
+    let mut orders = get_owner_orders(e, order.owner.clone());
+    if let Some(i) = orders.first_index_of(order_id) {
+        orders.remove(i);
+    }
+    put_owner_orders(e, order.owner.clone(), &orders);
+}
+
+// Returns the side of "pool" that sells "sell_token" for "buy_token". Panics unless the router deployed
+// "pool" for exactly that pair.
+fn check_pool(e: &Env, pool: &liquiditypool::Client, sell_token: &Address, buy_token: &Address) -> bool {
+    let registered = FactoryClient::new(e, &get_factory(e)).get_pool(sell_token, buy_token);
+    assert!(registered == Some(pool.address.clone()), "Pool not registered for the pair");
+
+    pool.get_tokens().0 == *sell_token
+}
+
+// The escrow is the payer of the pool swap, so the pool's transfer of the sold token out of this
+// contract has to be pre-authorized before calling the pool.
+fn authorize_pool_transfer(e: &Env, pool: &liquiditypool::Client, sell_token: Address, amount_in: i128) {
+    e.authorize_as_current_contract(vec![
+        e,
+        InvokerContractAuthEntry::Contract(SubContractInvocation {
+            context: ContractContext {
+                contract: sell_token,
+                fn_name: Symbol::new(e, "transfer"),
+                args: (e.current_contract_address(), pool.address.clone(), amount_in).into_val(e),
+            },
+            sub_invocations: vec![e],
+        }),
+    ]);
+}
+
+
+// Metadata that is added on to the WASM custom section
+contractmeta!(
+    key = "LimitOrder",
+    val = "Limit orders filled against SorobanSwap pools"
+);
+
+pub trait LimitOrderTrait {
+    // Sets the router whose pools orders can be placed on
+    fn initialize(e: Env, factory: Address);
+
+    // Escrows "amount_in" + "tip" of "sell_token" from "owner" and opens an order selling amount_in for at
+    // least "min_out" of "buy_token", executable until the "expiration" timestamp. "pool" must be the
+    // router's pool for the pair. Returns the order id.
+    fn place_order(e: Env, owner: Address, pool: Address, sell_token: Address, buy_token: Address, amount_in: i128, min_out: i128, tip: i128, expiration: u64) -> u32;
+
+    // Fills "order_id" through the pool's swap_exact_input, paying the output to the order owner and the
+    // tip to "executor". Anyone can execute an order, the pool rejects it while its price is below the limit.
+    // Returns the buy_token the owner actually received.
+    fn execute(e: Env, executor: Address, order_id: u32) -> i128;
+
+    // Closes "order_id" and refunds the escrowed amount and tip to its owner, expired or not
+    fn cancel(e: Env, order_id: u32);
+
+    fn get_order(e: Env, order_id: u32) -> Order;
+
+    // Returns the ids of the open orders of "owner"
+    fn get_orders(e: Env, owner: Address) -> Vec<u32>;
+}
+
+#[contract]
+struct LimitOrder;
+
+#[contractimpl]
+impl LimitOrderTrait for LimitOrder {
+    fn initialize(e: Env, factory: Address) {
+        if e.storage().instance().has(&DataKey::Factory) {
+            panic!("LimitOrder already initialized");
+        }
+
+        e.storage().instance().set(&DataKey::Factory, &factory);
+    }
+
+    fn place_order(e: Env, owner: Address, pool: Address, sell_token: Address, buy_token: Address, amount_in: i128, min_out: i128, tip: i128, expiration: u64) -> u32 {
+        owner.require_auth();
+
+        assert!(amount_in > 0 && min_out > 0, "Amount must be grater then 0");
+        assert!(tip >= 0, "tip can't be negative");
+        assert!(expiration > e.ledger().timestamp(), "expiration must be in the future");
+
+        let sell_a = check_pool(&e, &liquiditypool::Client::new(&e, &pool), &sell_token, &buy_token);
+        token::Client::new(&e, &sell_token).transfer(&owner, &e.current_contract_address(), &(amount_in + tip));
+
+        let order_id = get_order_count(&e);
+        put_order_count(&e, order_id + 1);
+
+        put_order(&e, order_id, &Order {
+            owner: owner.clone(),
+            pool,
+            sell_token,
+            buy_token,
+            sell_a,
+            amount_in,
+            min_out,
+            tip,
+            expiration,
+        });
+
+        let mut orders = get_owner_orders(&e, owner.clone());
+        orders.push_back(order_id);
+        put_owner_orders(&e, owner, &orders);
+
+        order_id
+    }
+
+    fn execute(e: Env, executor: Address, order_id: u32) -> i128 {
+        executor.require_auth();
+
+        let order = get_order(&e, order_id);
+        assert!(e.ledger().timestamp() < order.expiration, "Order expired");
+
+        remove_order(&e, order_id, &order);
+
+        // Measure what the owner receives rather than trusting the pool's return value
+        let pool = liquiditypool::Client::new(&e, &order.pool);
+        let buy_token = token::Client::new(&e, &order.buy_token);
+        let balance_before = buy_token.balance(&order.owner);
+
+        authorize_pool_transfer(&e, &pool, order.sell_token.clone(), order.amount_in);
+        pool.swap_exact_input(
+            &e.current_contract_address(),
+            &order.owner,
+            &order.sell_a,
+            &order.amount_in,
+            &order.min_out,
+            &None,
+            &None,
+        );
+
+        let amount_out = buy_token.balance(&order.owner) - balance_before;
+        assert!(amount_out >= order.min_out, "Not satisfied minimum out");
+
+        if order.tip > 0 {
+            token::Client::new(&e, &order.sell_token).transfer(&e.current_contract_address(), &executor, &order.tip);
+        }
+
+        amount_out
+    }
+
+    fn cancel(e: Env, order_id: u32) {
+        let order = get_order(&e, order_id);
+        order.owner.require_auth();
+
+        remove_order(&e, order_id, &order);
+
+        token::Client::new(&e, &order.sell_token).transfer(&e.current_contract_address(), &order.owner, &(order.amount_in + order.tip));
+    }
+
+    fn get_order(e: Env, order_id: u32) -> Order {
+        get_order(&e, order_id)
+    }
+
+    fn get_orders(e: Env, owner: Address) -> Vec<u32> {
+        get_owner_orders(&e, owner)
+    }
+}
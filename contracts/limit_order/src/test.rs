@@ -0,0 +1,247 @@
+#![cfg(test)]
+extern crate std;
+
+use crate::{liquiditypool, LimitOrder, LimitOrderClient};
+
+use soroban_sdk::{
+    contract, contractimpl,
+    testutils::{Address as _, Ledger, MockAuth, MockAuthInvoke},
+    token, vec, Address, Env, IntoVal, String,
+};
+
+mod token_contract {
+    soroban_sdk::contractimport!(
+        file = "../../target/wasm32-unknown-unknown/release/soroban_token_contract.wasm"
+    );
+}
+
+// Stands in for the router's pool registry
+#[contract]
+pub struct Factory;
+
+#[contractimpl]
+impl Factory {
+    pub fn set_pool(e: Env, token_a: Address, token_b: Address, pool: Address) {
+        e.storage().instance().set(&(token_a.clone(), token_b.clone()), &pool);
+        e.storage().instance().set(&(token_b, token_a), &pool);
+    }
+
+    pub fn get_pool(e: Env, token_a: Address, token_b: Address) -> Option<Address> {
+        e.storage().instance().get(&(token_a, token_b))
+    }
+}
+
+// Pool that reports whatever tokens it is told to and, when swapping, pulls "amount" of its current
+// token_a from the payer, whatever token the order sells, and pays "min_out" of token_b
+#[contract]
+pub struct SwitchingPool;
+
+#[contractimpl]
+impl SwitchingPool {
+    pub fn set_tokens(e: Env, token_a: Address, token_b: Address) {
+        e.storage().instance().set(&0u32, &(token_a, token_b));
+    }
+
+    pub fn get_tokens(e: Env) -> (Address, Address) {
+        e.storage().instance().get(&0u32).unwrap()
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn swap_exact_input(e: Env, from: Address, to: Address, _sell_a: bool, amount: i128, min_out: i128, _max_price_impact_bps: Option<u32>, _referrer: Option<Address>) -> i128 {
+        let (token_a, token_b) = Self::get_tokens(e.clone());
+        token::Client::new(&e, &token_a).transfer(&from, &e.current_contract_address(), &amount);
+        token::Client::new(&e, &token_b).transfer(&e.current_contract_address(), &to, &min_out);
+        min_out
+    }
+}
+
+fn create_orders_contract<'a>(e: &Env) -> (LimitOrderClient<'a>, FactoryClient<'a>) {
+    let factory = FactoryClient::new(e, &e.register_contract(None, Factory {}));
+    let orders = LimitOrderClient::new(e, &e.register_contract(None, LimitOrder {}));
+    orders.initialize(&factory.address);
+    (orders, factory)
+}
+
+fn create_token_contract<'a>(e: &Env, admin: &Address) -> token_contract::Client<'a> {
+    token_contract::Client::new(e, &e.register_stellar_asset_contract(admin.clone()))
+}
+
+fn create_liqpool_contract<'a>(e: &Env, admin: &Address, token_a: &Address, token_b: &Address) -> liquiditypool::Client<'a> {
+    let liqpool = liquiditypool::Client::new(e, &e.register_contract_wasm(None, liquiditypool::WASM));
+    liqpool.initialize(
+        &e.deployer().upload_contract_wasm(token_contract::WASM),
+        token_a,
+        token_b,
+        &Some(String::from_slice(e, "Pool Share")),
+        &Some(String::from_slice(e, "POOL")),
+        admin,
+        &None,
+    );
+    liqpool
+}
+
+#[test]
+fn test_order_executes_once_price_reaches_limit() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+    e.ledger().with_mut(|l| l.timestamp = 1_000);
+
+    let admin = Address::random(&e);
+    let owner = Address::random(&e);
+    let trader = Address::random(&e);
+    let executor = Address::random(&e);
+
+    let eth = create_token_contract(&e, &admin);
+    let usdc = create_token_contract(&e, &admin);
+    let liqpool = create_liqpool_contract(&e, &admin, &eth.address, &usdc.address);
+
+    eth.mint(&admin, &1_000_000);
+    usdc.mint(&admin, &1_000_000);
+    liqpool.deposit(&admin, &admin, &1_000_000, &0, &1_000_000, &0);
+
+    eth.mint(&owner, &10_100);
+    usdc.mint(&trader, &500_000);
+
+    let (orders, factory) = create_orders_contract(&e);
+    factory.set_pool(&eth.address, &usdc.address, &liqpool.address);
+
+    // Sell 10_000 ETH for at least 12_000 USDC, the pool currently pays less than 10_000
+    let order_id = orders.place_order(&owner, &liqpool.address, &eth.address, &usdc.address, &10_000, &12_000, &100, &2_000);
+    assert_eq!(orders.get_orders(&owner), vec![&e, order_id]);
+    assert_eq!(eth.balance(&orders.address), 10_100);
+
+    // Buying ETH pushes its price above the limit
+    liqpool.swap_exact_input(&trader, &trader, &false, &500_000, &0, &None, &None);
+
+    let amount_out = orders.execute(&executor, &order_id);
+    assert!(amount_out >= 12_000);
+    assert_eq!(usdc.balance(&owner), amount_out);
+    assert_eq!(eth.balance(&executor), 100);
+    assert_eq!(eth.balance(&orders.address), 0);
+    assert_eq!(orders.get_orders(&owner), vec![&e]);
+}
+
+// The host of the pinned SDK can't roll back a failed call once the orders contract authorized the pool's
+// transfer, so rejected executions are tested as panics rather than through try_execute
+#[test]
+#[should_panic]
+fn test_order_below_limit_is_not_executed() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+    e.ledger().with_mut(|l| l.timestamp = 1_000);
+
+    let admin = Address::random(&e);
+    let owner = Address::random(&e);
+    let executor = Address::random(&e);
+
+    let eth = create_token_contract(&e, &admin);
+    let usdc = create_token_contract(&e, &admin);
+    let liqpool = create_liqpool_contract(&e, &admin, &eth.address, &usdc.address);
+
+    eth.mint(&admin, &1_000_000);
+    usdc.mint(&admin, &1_000_000);
+    liqpool.deposit(&admin, &admin, &1_000_000, &0, &1_000_000, &0);
+    eth.mint(&owner, &10_100);
+
+    let (orders, factory) = create_orders_contract(&e);
+    factory.set_pool(&eth.address, &usdc.address, &liqpool.address);
+
+    // The pool pays less than 10_000 USDC for 10_000 ETH
+    let order_id = orders.place_order(&owner, &liqpool.address, &eth.address, &usdc.address, &10_000, &12_000, &100, &2_000);
+    orders.execute(&executor, &order_id);
+}
+
+#[test]
+fn test_expired_order_is_refunded_on_cancel() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+    e.ledger().with_mut(|l| l.timestamp = 1_000);
+
+    let admin = Address::random(&e);
+    let owner = Address::random(&e);
+    let executor = Address::random(&e);
+
+    let eth = create_token_contract(&e, &admin);
+    let usdc = create_token_contract(&e, &admin);
+    let liqpool = create_liqpool_contract(&e, &admin, &eth.address, &usdc.address);
+
+    eth.mint(&owner, &10_100);
+
+    let (orders, factory) = create_orders_contract(&e);
+    factory.set_pool(&eth.address, &usdc.address, &liqpool.address);
+    let order_id = orders.place_order(&owner, &liqpool.address, &eth.address, &usdc.address, &10_000, &1, &100, &2_000);
+
+    e.ledger().with_mut(|l| l.timestamp = 2_000);
+    assert!(orders.try_execute(&executor, &order_id).is_err());
+
+    orders.cancel(&order_id);
+    assert_eq!(eth.balance(&owner), 10_100);
+    assert!(orders.try_get_order(&order_id).is_err());
+}
+
+#[test]
+fn test_order_rejects_unregistered_pool() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+    e.ledger().with_mut(|l| l.timestamp = 1_000);
+
+    let admin = Address::random(&e);
+    let owner = Address::random(&e);
+
+    let eth = create_token_contract(&e, &admin);
+    let usdc = create_token_contract(&e, &admin);
+    let liqpool = create_liqpool_contract(&e, &admin, &eth.address, &usdc.address);
+    eth.mint(&owner, &10_100);
+
+    let (orders, _) = create_orders_contract(&e);
+    assert!(orders.try_place_order(&owner, &liqpool.address, &eth.address, &usdc.address, &10_000, &1, &100, &2_000).is_err());
+    assert_eq!(eth.balance(&owner), 10_100);
+}
+
+#[test]
+#[should_panic]
+fn test_switching_pool_cannot_take_other_orders_escrow() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+    e.ledger().with_mut(|l| l.timestamp = 1_000);
+
+    let admin = Address::random(&e);
+    let owner = Address::random(&e);
+    let attacker = Address::random(&e);
+
+    let eth = create_token_contract(&e, &admin);
+    let usdc = create_token_contract(&e, &admin);
+    let junk = create_token_contract(&e, &admin);
+    let liqpool = create_liqpool_contract(&e, &admin, &eth.address, &usdc.address);
+    eth.mint(&owner, &10_100);
+    junk.mint(&attacker, &10_000);
+
+    let (orders, factory) = create_orders_contract(&e);
+    factory.set_pool(&eth.address, &usdc.address, &liqpool.address);
+    orders.place_order(&owner, &liqpool.address, &eth.address, &usdc.address, &10_000, &12_000, &100, &2_000);
+
+    // Even if a hostile pool got registered, it is held to the tokens fixed when its order was placed
+    let pool = SwitchingPoolClient::new(&e, &e.register_contract(None, SwitchingPool {}));
+    pool.set_tokens(&junk.address, &usdc.address);
+    usdc.mint(&pool.address, &1);
+    factory.set_pool(&junk.address, &usdc.address, &pool.address);
+    let order_id = orders.place_order(&attacker, &pool.address, &junk.address, &usdc.address, &10_000, &1, &0, &2_000);
+
+    // The pool now claims to sell ETH and pulls the other order's escrow
+    pool.set_tokens(&eth.address, &usdc.address);
+    e.mock_auths(&[MockAuth {
+        address: &attacker,
+        invoke: &MockAuthInvoke {
+            contract: &orders.address,
+            fn_name: "execute",
+            args: (&attacker, order_id).into_val(&e),
+            sub_invokes: &[],
+        },
+    }]);
+    orders.execute(&attacker, &order_id);
+}
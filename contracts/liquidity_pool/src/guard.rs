@@ -0,0 +1,20 @@
+use soroban_sdk::{Env, IntoVal, Val};
+
+// Re-entrancy guard of the pools, kept under "key" in the calling pool's instance storage.
+// Taken by every mutating entrypoint before it calls out to token contracts and released once the
+// reserves are updated. A panic reverts the whole invocation, so the lock never stays taken.
+// The host already refuses any call into a contract that is on the call stack, directly or through
+// other contracts, so no call can currently find the lock taken. It only matters if that host check
+// is ever relaxed, and tests can only exercise it by taking the lock themselves.
+pub fn lock<K: IntoVal<Env, Val>>(e: &Env, key: &K) {
+    let locked: bool = e.storage().instance().get(key).unwrap_or(false);
+
+    if locked {
+        panic!("Reentrant call");
+    }
+    e.storage().instance().set(key, &true);
+}
+
+pub fn unlock<K: IntoVal<Env, Val>>(e: &Env, key: &K) {
+    e.storage().instance().set(key, &false);
+}
@@ -0,0 +1,24 @@
+use soroban_sdk::{contractclient, Address, Env};
+
+// Interface of the optional hooks contract registered at initialize. The pool calls it from inside its
+// own entrypoints with its address as "pool", so one hooks contract can serve several pools. A hook
+// vetoes the operation by panicking. The host refuses to re-enter a contract that is already running, so
+// hooks can't call back into the pool, not even its views, and get what they need as arguments.
+#[allow(dead_code)]
+#[contractclient(name = "HooksClient")]
+pub trait Hooks {
+    fn before_deposit(e: Env, pool: Address, from: Address, to: Address, amount_a: i128, amount_b: i128);
+
+    fn after_deposit(e: Env, pool: Address, from: Address, to: Address, amount_a: i128, amount_b: i128, shares: i128);
+
+    // "amount" is the input of an exact input swap and the output of an exact output swap. Returns the
+    // fee in basis points the swap will pay, return "fee_bps" to keep the pool's fee. Also called by
+    // quote_swap_fee, so it can't assume a swap follows.
+    fn before_swap(e: Env, pool: Address, from: Address, sell_a: bool, amount: i128, exact_input: bool, fee_bps: i128) -> i128;
+
+    fn after_swap(e: Env, pool: Address, from: Address, to: Address, sell_a: bool, amount_in: i128, amount_out: i128);
+
+    fn before_withdraw(e: Env, pool: Address, from: Address, to: Address, shares: i128);
+
+    fn after_withdraw(e: Env, pool: Address, from: Address, to: Address, shares: i128, amount_a: i128, amount_b: i128);
+}
@@ -1,18 +1,33 @@
 #![no_std]
+#![allow(clippy::too_many_arguments)]
 
 mod test;
 mod lptoken;
+mod hooks;
+mod guard;
+mod math;
 
 
 use num_integer::Roots;
 use soroban_sdk::{
-    contract, contractimpl, contractmeta, Address, BytesN, ConversionError, Env, IntoVal,
-    TryFromVal, Val, token, String,
+    contract, contractimpl, contractmeta, contracttype, Address, BytesN, ConversionError, Env, IntoVal,
+    TryFromVal, Val, token, String, symbol_short,
 };
-use lptoken::create_contract;
+use lptoken::{create_contract, lp_token_metadata};
+use hooks::HooksClient;
+use math::mul_div;
 
 const MINIMUN_LIQUIDITY:i128 = 1000;
 
+// Swap fee in basis points when the dynamic fee is off
+const BASE_FEE_BPS:i128 = 25;
+
+// Seconds over which the recorded volatility decays by half
+const VOLATILITY_HALF_LIFE:u64 = 600;
+
+// Highest fraction of the swap fee, in basis points, the admin can route to referrers
+const MAX_REFERRAL_SHARE_BPS:u32 = 5000;
+
 #[derive(Clone, Copy)]
 #[repr(u32)]
 pub enum DataKey {
@@ -23,7 +38,68 @@ pub enum DataKey {
     ReserveA = 4,
     ReserveB = 5,
     KLast = 6,
-    IsPoolInitialize=7
+    IsPoolInitialize=7,
+    Locked = 8,
+    Admin = 9,
+    BreakerMaxMoveBps = 10,
+    BreakerWindow = 11,
+    BreakerStartLedger = 12,
+    BreakerReserveA = 13,
+    BreakerReserveB = 14,
+    DynamicFeeMin = 15,
+    DynamicFeeMax = 16,
+    DynamicFeeSlope = 17,
+    Volatility = 18,
+    Stats = 19,
+    ReferralShareBps = 20,
+    Hooks = 21,
+    Permissioned = 22,
+    VolatilityUpdated = 23
+}
+
+// Cumulative swap volume per token since the pool was created. Fees are charged on the
+// sold token, so fees_a accrues from swaps selling token_a and fees_b from swaps selling token_b.
+// Fees are what stays with the LPs, after referral payouts.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PoolStats {
+    pub volume_in_a: i128,
+    pub volume_in_b: i128,
+    pub volume_out_a: i128,
+    pub volume_out_b: i128,
+    pub fees_a: i128,
+    pub fees_b: i128,
+}
+
+// Per address keys, kept in persistent storage
+#[contracttype]
+#[derive(Clone)]
+pub enum UserDataKey {
+    Position(Address),
+    Allowed(Address),
+}
+
+// Cost basis of an address: tokens deposited for it, reduced proportionally as it withdraws.
+// LP tokens moved outside the pool carry no cost basis with them.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LpPosition {
+    pub deposited_a: i128,
+    pub deposited_b: i128,
+}
+
+// Position of an address valued at the current reserves. Values "in_b" price token_a at the current
+// pool price, so lp_value_in_b / hold_value_in_b - 1 is the impermanent loss net of fees earned.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PositionInfo {
+    pub shares: i128,
+    pub value_a: i128,
+    pub value_b: i128,
+    pub deposited_a: i128,
+    pub deposited_b: i128,
+    pub lp_value_in_b: i128,
+    pub hold_value_in_b: i128,
 }
 
 
@@ -50,6 +126,267 @@ fn check_pool_initialize(e: &Env){
     
 }
 
+fn lock(e: &Env) {
+    guard::lock(e, &DataKey::Locked);
+}
+
+fn unlock(e: &Env) {
+    guard::unlock(e, &DataKey::Locked);
+}
+
+fn get_admin(e: &Env) -> Address {
+    e.storage().instance().get(&DataKey::Admin).unwrap()
+}
+
+fn put_admin(e: &Env, admin: Address) {
+    e.storage().instance().set(&DataKey::Admin, &admin);
+}
+
+fn get_breaker_config(e: &Env) -> (u32, u32) {
+    (
+        e.storage().instance().get(&DataKey::BreakerMaxMoveBps).unwrap_or(0),
+        e.storage().instance().get(&DataKey::BreakerWindow).unwrap_or(1),
+    )
+}
+
+// Returns the reserves recorded at the start of the current breaker window. The first swap after the
+// window has elapsed starts a new one from the reserves it sees before trading.
+fn get_breaker_start_reserves(e: &Env, window: u32, reserve_a: i128, reserve_b: i128) -> (i128, i128) {
+    let ledger = e.ledger().sequence();
+    let start_ledger: Option<u32> = e.storage().instance().get(&DataKey::BreakerStartLedger);
+
+    match start_ledger {
+        Some(start) if ledger < start.saturating_add(window) => (
+            e.storage().instance().get(&DataKey::BreakerReserveA).unwrap(),
+            e.storage().instance().get(&DataKey::BreakerReserveB).unwrap(),
+        ),
+        _ => {
+            e.storage().instance().set(&DataKey::BreakerStartLedger, &ledger);
+            e.storage().instance().set(&DataKey::BreakerReserveA, &reserve_a);
+            e.storage().instance().set(&DataKey::BreakerReserveB, &reserve_b);
+            (reserve_a, reserve_b)
+        }
+    }
+}
+
+// Rejects a swap that would move the price of token_a in token_b by more than the configured
+// basis points from the price at the start of the window. Deposits and withdrawals are not checked.
+fn check_circuit_breaker(e: &Env, reserve_a: i128, reserve_b: i128, new_reserve_a: i128, new_reserve_b: i128) {
+    let (max_move_bps, window) = get_breaker_config(e);
+    if max_move_bps == 0 {
+        return;
+    }
+
+    let (start_a, start_b) = get_breaker_start_reserves(e, window, reserve_a, reserve_b);
+    if start_a == 0 || start_b == 0 {
+        return;
+    }
+
+    // start price is start_b / start_a and the new price new_reserve_b / new_reserve_a. Both are compared as
+    // the token_b paid for start_a of token_a, with 256 bit products so 18 decimal reserves don't overflow.
+    let moved = (mul_div(new_reserve_b, start_a, new_reserve_a) - start_b).abs();
+
+    if moved > mul_div(start_b, max_move_bps as i128, 10000) {
+        panic!("circuit breaker: price moved too much in this window");
+    }
+}
+
+// Returns (min_fee_bps, max_fee_bps, slope_bps). max_fee_bps is 0 while the dynamic fee is off.
+fn get_dynamic_fee_config(e: &Env) -> (u32, u32, u32) {
+    (
+        e.storage().instance().get(&DataKey::DynamicFeeMin).unwrap_or(0),
+        e.storage().instance().get(&DataKey::DynamicFeeMax).unwrap_or(0),
+        e.storage().instance().get(&DataKey::DynamicFeeSlope).unwrap_or(0),
+    )
+}
+
+// Recorded volatility decayed to the current ledger time. It halves every VOLATILITY_HALF_LIFE seconds,
+// interpolating linearly within a half-life.
+fn get_volatility(e: &Env) -> i128 {
+    let volatility: i128 = e.storage().instance().get(&DataKey::Volatility).unwrap_or(0);
+    let updated: u64 = e.storage().instance().get(&DataKey::VolatilityUpdated).unwrap_or(0);
+
+    let elapsed = e.ledger().timestamp().saturating_sub(updated);
+    let halvings = elapsed / VOLATILITY_HALF_LIFE;
+    if halvings >= 64 {
+        return 0;
+    }
+
+    let halved = volatility >> halvings;
+    let rest = (elapsed % VOLATILITY_HALF_LIFE) as i128;
+    halved - halved * rest / (2 * VOLATILITY_HALF_LIFE as i128)
+}
+
+// Current swap fee in basis points. In dynamic mode it grows with the recorded volatility by
+// "slope_bps" per 100% of volatility, bounded by the configured min and max.
+fn get_fee_bps(e: &Env) -> i128 {
+    let (min_fee, max_fee, slope) = get_dynamic_fee_config(e);
+    if max_fee == 0 {
+        return BASE_FEE_BPS;
+    }
+
+    let fee = min_fee as i128 + get_volatility(e) * slope as i128 / 10000;
+    fee.min(max_fee as i128)
+}
+
+// Adds the price change of a swap, in basis points, to the decayed volatility. A swap counts in proportion
+// to how far it moved the price, so dust swaps add nothing and, as decay only follows ledger time, can't
+// wear the volatility down either. Only tracked in dynamic fee mode.
+fn record_volatility(e: &Env, reserve_a: i128, reserve_b: i128, new_reserve_a: i128, new_reserve_b: i128) {
+    let (_, max_fee, _) = get_dynamic_fee_config(e);
+    if max_fee == 0 || reserve_a == 0 || reserve_b == 0 {
+        return;
+    }
+
+    // Price change of token_a in token_b, with 256 bit products so 18 decimal reserves don't overflow
+    let change = (mul_div(new_reserve_b, reserve_a, new_reserve_a) - reserve_b).abs();
+    let change_bps = mul_div(change, 10000, reserve_b);
+    let volatility = get_volatility(e) + change_bps;
+
+    e.storage().instance().set(&DataKey::Volatility, &volatility);
+    e.storage().instance().set(&DataKey::VolatilityUpdated, &e.ledger().timestamp());
+}
+
+fn get_stats(e: &Env) -> PoolStats {
+    e.storage().instance().get(&DataKey::Stats).unwrap_or(PoolStats {
+        volume_in_a: 0,
+        volume_in_b: 0,
+        volume_out_a: 0,
+        volume_out_b: 0,
+        fees_a: 0,
+        fees_b: 0,
+    })
+}
+
+// Records the fee kept by the pool, the part of it paid to a referrer is left out
+fn record_swap_stats(e: &Env, sell_a: bool, amount_in: i128, amount_out: i128, fee_bps: i128, referral: i128) {
+    let mut stats = get_stats(e);
+    let fee = amount_in * fee_bps / 10000 - referral;
+
+    if sell_a {
+        stats.volume_in_a += amount_in;
+        stats.volume_out_b += amount_out;
+        stats.fees_a += fee;
+    } else {
+        stats.volume_in_b += amount_in;
+        stats.volume_out_a += amount_out;
+        stats.fees_b += fee;
+    }
+
+    e.storage().instance().set(&DataKey::Stats, &stats);
+}
+
+// Persistent entries stay alive for 30 days after they were last read or written
+const DAY_IN_LEDGERS: u32 = 17280;
+const PERSISTENT_BUMP_AMOUNT: u32 = 30 * DAY_IN_LEDGERS;
+const PERSISTENT_LIFETIME_THRESHOLD: u32 = PERSISTENT_BUMP_AMOUNT - DAY_IN_LEDGERS;
+
+// Reads a persistent entry, extending its lifetime when it exists
+fn get_persistent<K: IntoVal<Env, Val>, V: TryFromVal<Env, Val>>(e: &Env, key: &K) -> Option<V> {
+    let value = e.storage().persistent().get(key);
+    if value.is_some() {
+        e.storage()
+            .persistent()
+            .bump(key, PERSISTENT_LIFETIME_THRESHOLD, PERSISTENT_BUMP_AMOUNT);
+    }
+    value
+}
+
+fn put_persistent<K: IntoVal<Env, Val>, V: IntoVal<Env, Val>>(e: &Env, key: &K, value: &V) {
+    e.storage().persistent().set(key, value);
+    e.storage()
+        .persistent()
+        .bump(key, PERSISTENT_LIFETIME_THRESHOLD, PERSISTENT_BUMP_AMOUNT);
+}
+
+fn is_permissioned(e: &Env) -> bool {
+    e.storage().instance().get(&DataKey::Permissioned).unwrap_or(false)
+}
+
+fn is_allowed(e: &Env, id: Address) -> bool {
+    get_persistent(e, &UserDataKey::Allowed(id)).unwrap_or(false)
+}
+
+// In permissioned mode only allowlisted addresses may deposit, swap or receive from the pool
+fn check_allowed(e: &Env, id: &Address) {
+    if is_permissioned(e) && !is_allowed(e, id.clone()) {
+        panic!("address not allowed");
+    }
+}
+
+fn get_hooks(e: &Env) -> Option<Address> {
+    e.storage().instance().get(&DataKey::Hooks)
+}
+
+fn hooks<'a>(e: &Env) -> Option<HooksClient<'a>> {
+    get_hooks(e).map(|hooks| HooksClient::new(e, &hooks))
+}
+
+// Fee in basis points for a swap, as adjusted by the hooks contract if the pool has one
+fn get_swap_fee_bps(e: &Env, from: &Address, sell_a: bool, amount: i128, exact_input: bool) -> i128 {
+    let fee_bps = get_fee_bps(e);
+
+    match hooks(e) {
+        Some(hooks) => {
+            let fee_bps = hooks.before_swap(&e.current_contract_address(), from, &sell_a, &amount, &exact_input, &fee_bps);
+            assert!((0..10000).contains(&fee_bps), "invalid hook fee");
+            fee_bps
+        }
+        None => fee_bps,
+    }
+}
+
+fn get_referral_share_bps(e: &Env) -> u32 {
+    e.storage().instance().get(&DataKey::ReferralShareBps).unwrap_or(0)
+}
+
+// Pays "referrer" its share of the fee charged on "amount_in", in the sold token the pool just received.
+// Returns the amount paid, which the caller has to take out of the new reserves.
+fn pay_referral(e: &Env, referrer: &Option<Address>, sell_token: Address, amount_in: i128, fee_bps: i128) -> i128 {
+    let referrer = match referrer {
+        Some(referrer) => referrer.clone(),
+        None => return 0,
+    };
+
+    let amount = amount_in * fee_bps / 10000 * get_referral_share_bps(e) as i128 / 10000;
+    if amount > 0 {
+        transfer(e, sell_token.clone(), referrer.clone(), amount);
+        e.events().publish((symbol_short!("referral"), referrer, sell_token), amount);
+    }
+
+    amount
+}
+
+fn get_lp_position(e: &Env, id: Address) -> LpPosition {
+    get_persistent(e, &UserDataKey::Position(id)).unwrap_or(LpPosition {
+        deposited_a: 0,
+        deposited_b: 0,
+    })
+}
+
+fn put_lp_position(e: &Env, id: Address, position: LpPosition) {
+    put_persistent(e, &UserDataKey::Position(id), &position);
+}
+
+fn record_deposit(e: &Env, id: Address, amount_a: i128, amount_b: i128) {
+    let mut position = get_lp_position(e, id.clone());
+    position.deposited_a += amount_a;
+    position.deposited_b += amount_b;
+    put_lp_position(e, id, position);
+}
+
+// Releases the share of the cost basis that belongs to the withdrawn shares
+fn record_withdraw(e: &Env, id: Address, shares_before: i128, shares_withdrawn: i128) {
+    if shares_before == 0 || shares_withdrawn == 0 {
+        return;
+    }
+
+    let mut position = get_lp_position(e, id.clone());
+    position.deposited_a -= position.deposited_a * shares_withdrawn / shares_before;
+    position.deposited_b -= position.deposited_b * shares_withdrawn / shares_before;
+    put_lp_position(e, id, position);
+}
+
 fn get_token_a(e: &Env) -> Address {
     e.storage().instance().get(&DataKey::TokenA).unwrap()
     
@@ -87,12 +424,8 @@ fn get_balance_b(e: &Env) -> i128 {
     get_balance(e, get_token_b(e))
 }
 
-fn get_balance_shares(e: &Env) -> i128 {
-    get_balance(e, get_token_share(e))
-}
-
 fn get_lp_balance(e:&Env,id:Address)->i128{
-    token::Client::new(e,&get_token_share(&e)).balance(&id)
+    token::Client::new(e,&get_token_share(e)).balance(&id)
 }
 
 fn get_k_last(e: &Env)->i128 {
@@ -138,6 +471,15 @@ fn burn_shares(e: &Env,id:Address, amount: i128) {
     put_total_shares(e, total - amount);
 }
 
+fn burn_shares_from(e: &Env, spender: Address, from: Address, amount: i128) {
+    let total = get_total_shares(e);
+    let share_contract = get_token_share(e);
+
+    lptoken::Client::new(e, &share_contract).burn_from(&spender, &from, &amount);
+
+    put_total_shares(e, total - amount);
+}
+
 fn mint_shares(e: &Env, to: Address, amount: i128) {
     let total = get_total_shares(e);
     let share_contract_id = get_token_share(e);
@@ -169,18 +511,91 @@ fn total_lp_supply(e:&Env)->i128{
 pub fn get_amount_out(
     amount_in: i128,
     reserve_in: i128,
-    reserve_out: i128
+    reserve_out: i128,
+    fee_bps: i128
 )->i128 {
     assert!(amount_in > 0, "Amount must be grater then 0");
     assert!(reserve_in > 0 && reserve_out > 0, "ERROR_INSUFFICIENT_LIQUIDITY");
 
-    let amount_in_with_fee = amount_in * 9975;
+    let amount_in_with_fee = amount_in * (10000 - fee_bps);
     let numerator = amount_in_with_fee * reserve_out;
     let denominator = reserve_in * 10000 + amount_in_with_fee;
 
     numerator / denominator
 }
 
+// Panics if moving "amount_in" into and "amount_out" out of the reserves lowers the spot price of the
+// output token (reserve_out / reserve_in) by more than "max_price_impact_bps". None disables the check.
+fn check_price_impact(
+    amount_in: i128,
+    amount_out: i128,
+    reserve_in: i128,
+    reserve_out: i128,
+    max_price_impact_bps: Option<u32>,
+) {
+    if let Some(max_bps) = max_price_impact_bps {
+        // price_after / price_before, scaled by 10000
+        let numerator = (reserve_out - amount_out) * reserve_in * 10000;
+        let denominator = reserve_out * (reserve_in + amount_in);
+        let impact_bps = 10000 - numerator / denominator;
+
+        if impact_bps > max_bps as i128 {
+            panic!("price impact too high");
+        }
+    }
+}
+
+// Burns "liquidity" shares of "from" and sends the underlying tokens to "to". With a spender the shares
+// are burned through the share token's burn_from, consuming the allowance "from" gave to "spender".
+fn withdraw_liquidity(
+    e: &Env,
+    from: Address,
+    spender: Option<Address>,
+    to: Address,
+    liquidity: i128,
+    amount_x_min: i128,
+    amount_y_min: i128,
+) -> (i128, i128) {
+    if let Some(hooks) = hooks(e) {
+        hooks.before_withdraw(&e.current_contract_address(), &from, &to, &liquidity);
+    }
+
+    // The shares are burned from "from" once the amounts are known, through the spender's allowance for withdraw_from
+    let share_token_client = token::Client::new(e, &get_token_share(e));
+
+    let (balance_a, balance_b) = (get_balance_a(e), get_balance_b(e));
+
+    let shares_before = share_token_client.balance(&from);
+    assert!(shares_before >= liquidity,"Don't have a enough liquidity to remove");
+
+    let total_shares = total_lp_supply(e);
+
+    // Now calculate the withdraw amounts
+    let out_a = (balance_a * liquidity) / total_shares;
+    let out_b = (balance_b * liquidity) / total_shares;
+
+    if out_a < amount_x_min || out_b < amount_y_min {
+        panic!("min not satisfied");
+    }
+
+    record_withdraw(e, from.clone(), shares_before, liquidity);
+    match spender {
+        Some(spender) => burn_shares_from(e, spender, from.clone(), liquidity),
+        None => burn_shares(e, from.clone(), liquidity),
+    }
+    transfer_a(e, to.clone(), out_a);
+    transfer_b(e, to.clone(), out_b);
+    put_reserve_a(e, balance_a - out_a);
+    put_reserve_b(e, balance_b - out_b);
+    put_k_last(e,get_reserve_a(e) * get_reserve_a(e));
+
+    if let Some(hooks) = hooks(e) {
+        hooks.after_withdraw(&e.current_contract_address(), &from, &to, &liquidity, &out_a, &out_b);
+    }
+
+    (out_a, out_b)
+}
+
 fn get_deposit_amounts(
     desired_a: i128,
     min_a: i128,
@@ -215,36 +630,104 @@ contractmeta!(
 );
 
 pub trait LiquidityPoolTrait {
-    // Sets the token contract addresses for this pool
-    fn initialize(e: Env, token_wasm_hash: BytesN<32>, token_a: Address, token_b: Address,lptokenname:String,lptokensymbol:String);
+    // Sets the token contract addresses for this pool, and the admin allowed to configure it.
+    // The share token name and symbol are derived from the tokens' symbols unless overridden.
+    // An optional "hooks" contract (see hooks::Hooks) is called before and after deposits, swaps and withdrawals.
+    fn initialize(e: Env, token_wasm_hash: BytesN<32>, token_a: Address, token_b: Address,lptokenname:Option<String>,lptokensymbol:Option<String>,admin:Address,hooks:Option<Address>);
 
     // Returns the token contract address for the pool share token
     fn share_id(e: Env) -> Address;
 
-    // Deposits token_a and token_b. Also mints pool shares for the "to" Identifier. The amount minted
+    // Deposits token_a and token_b from "from". Also mints pool shares for the "to" Identifier. The amount minted
     // is determined based on the difference between the reserves stored by this contract, and
     // the actual balance of token_a and token_b for this contract.
-    fn deposit(e: Env, to: Address, desired_a: i128, min_a: i128, desired_b: i128, min_b: i128)->(i128,i128,i128,i128,i128);
+    fn deposit(e: Env, from: Address, to: Address, desired_a: i128, min_a: i128, desired_b: i128, min_b: i128)->(i128,i128,i128,i128,i128);
 
     // If "swap_x_to_y" is true, the swap will buy token_a and sell token_b. This is flipped if "swap_x_to_y" is false.
     // "out" is the amount being bought, with in_max being a safety to make sure you receive at least that amount.
-    // swap will transfer the selling token "to" to this contract, and then the contract will transfer the buying token to "to".
-    fn swap_exact_input(e: Env, to: Address, swap_x_to_y: bool, out: i128, in_max: i128)->i128;
+    // swap will transfer the selling token from "from" to this contract, and then the contract will transfer the buying token to "to".
+    // "max_price_impact_bps" optionally rejects swaps that move the spot price of the bought token by more than that many basis points.
+    // An optional "referrer" is paid the referral share of the swap fee in the sold token.
+    fn swap_exact_input(e: Env, from: Address, to: Address, swap_x_to_y: bool, out: i128, in_max: i128, max_price_impact_bps: Option<u32>, referrer: Option<Address>)->i128;
     
-    fn swap_exact_output(e: Env, to: Address, swap_x_to_y: bool, out: i128, in_max: i128)->i128;
-    // transfers share_amount of pool share tokens to this contract, burns all pools share tokens in this contracts, and sends the
+    fn swap_exact_output(e: Env, from: Address, to: Address, swap_x_to_y: bool, out: i128, in_max: i128, max_price_impact_bps: Option<u32>, referrer: Option<Address>)->i128;
+
+    // Same as swap_exact_input, but the output is quoted from the amount that actually arrived in the pool
+    // (balance minus reserve) instead of the nominal x_in, so tokens that take a fee on transfer can be sold.
+    // Contract function names are limited to 32 characters, which rules out swap_exact_input_supporting_fee_on_transfer.
+    fn swap_exact_in_fee_on_transfer(e: Env, from: Address, to: Address, swap_x_to_y: bool, x_in: i128, y_min_out: i128, max_price_impact_bps: Option<u32>, referrer: Option<Address>)->i128;
+
+    // burns share_amount of pool share tokens owned by "from", and sends the
     // corresponding amount of token_a and token_b to "to".
     // Returns amount of both tokens withdrawn
-    fn withdraw(e: Env, to: Address, share_amount: i128, min_a: i128, min_b: i128) -> (i128, i128);
+    fn withdraw(e: Env, from: Address, to: Address, share_amount: i128, min_a: i128, min_b: i128) -> (i128, i128);
+
+    // Same as withdraw, but "spender" redeems shares of "from" using the LP token allowance "from" approved for it
+    fn withdraw_from(e: Env, spender: Address, from: Address, to: Address, share_amount: i128, min_a: i128, min_b: i128) -> (i128, i128);
 
     fn get_rsrvs(e: Env) -> (i128, i128);
 
+    // Returns the token_a and token_b contract addresses of this pool
+    fn get_tokens(e: Env) -> (Address, Address);
+
     fn get_lptoken_balance(e: Env,id:Address)->i128;
 
     fn get_contract_lptoken_balance(e: Env)->i128;
 
     fn get_k(e: Env) -> i128;
 
+    // Halts swaps that move the price by more than "max_move_bps" within "window_ledgers" ledgers,
+    // measured from the reserves at the start of the window. Withdrawals stay open. 0 disables the breaker.
+    fn set_circuit_breaker(e: Env, max_move_bps: u32, window_ledgers: u32);
+
+    // Returns (max_move_bps, window_ledgers)
+    fn get_circuit_breaker(e: Env) -> (u32, u32);
+
+    // Switches to a fee of min_fee_bps + volatility * slope_bps / 10000, capped at max_fee_bps, where volatility
+    // is the sum of the price changes of swaps in basis points, halving every VOLATILITY_HALF_LIFE seconds.
+    // A max_fee_bps of 0 goes back to the fixed 0.25% fee.
+    fn set_dynamic_fee(e: Env, min_fee_bps: u32, max_fee_bps: u32, slope_bps: u32);
+
+    // Returns the pool's fee in basis points, before the hooks adjust it
+    fn get_fee(e: Env) -> i128;
+
+    // Returns the fee in basis points an exact input swap of "amount" paid by "from" would pay, as adjusted
+    // by the hooks. Panics if the hooks would veto that swap.
+    fn quote_swap_fee(e: Env, from: Address, sell_a: bool, amount: i128) -> i128;
+
+    // Returns the volatility in basis points, decayed to the current ledger time
+    fn get_volatility(e: Env) -> i128;
+
+    // Returns the cumulative volume in and out and the fees earned per token
+    fn get_stats(e: Env) -> PoolStats;
+
+    // Routes "share_bps" of the swap fee to the referrer of a swap, at most MAX_REFERRAL_SHARE_BPS. 0 turns referrals off.
+    fn set_referral_share(e: Env, share_bps: u32);
+
+    fn get_referral_share(e: Env) -> u32;
+
+    // Returns the hooks contract registered at initialize, if any
+    fn get_hooks(e: Env) -> Option<Address>;
+
+    // Turns permissioned mode on or off. While on, both "from" and "to" of deposits and swaps must be on the
+    // allowlist, and the share token only mints or transfers shares to allowed addresses. Withdrawals stay open
+    // to every holder. Allowing a contract that acts for whoever calls it, like the router or limit orders,
+    // lets anyone trade through it, so only allow contracts such as vaults that gate their own users.
+    // To delegate the allowlist to a registry, leave this off and veto in the hooks contract's before_deposit
+    // and before_swap instead.
+    fn set_permissioned(e: Env, permissioned: bool);
+
+    fn is_permissioned(e: Env) -> bool;
+
+    // Adds "id" to or removes it from the allowlist, for the pool and its share token
+    fn set_allowed(e: Env, id: Address, allowed: bool);
+
+    fn is_allowed(e: Env, id: Address) -> bool;
+
+    // Returns the shares of "id", their underlying value at the current reserves and the cost basis,
+    // with both valued in token_b so impermanent loss can be read directly
+    fn get_position(e: Env, id: Address) -> PositionInfo;
+
 }
 
 #[contract]
@@ -252,48 +735,67 @@ struct LiquidityPool;
 
 #[contractimpl]
 impl LiquidityPoolTrait for LiquidityPool {
-    fn initialize(e: Env, token_wasm_hash: BytesN<32>, token_a: Address, token_b: Address,lptokenname:String,lptokensymbol:String) {
+    fn initialize(e: Env, token_wasm_hash: BytesN<32>, token_a: Address, token_b: Address,lptokenname:Option<String>,lptokensymbol:Option<String>,admin:Address,hooks:Option<Address>) {
         // if token_a >= token_b {
         //     panic!("token_a must be less than token_b");
         // }
         
         check_pool_initialize(&e);
     
+        let (lptokenname, lptokensymbol) = match (lptokenname, lptokensymbol) {
+            (Some(name), Some(symbol)) => (name, symbol),
+            (name, symbol) => {
+                let (derived_name, derived_symbol) = lp_token_metadata(&e, &token_a, &token_b);
+                (name.unwrap_or(derived_name), symbol.unwrap_or(derived_symbol))
+            }
+        };
+
         let share_contract = create_contract(&e, token_wasm_hash, &token_a, &token_b);
         lptoken::Client::new(&e, &share_contract).initialize(
             &e.current_contract_address(),
             &8u32,
-            &lptokenname.into_val(&e),
-            &&lptokensymbol.into_val(&e),
+            &lptokenname,
+            &lptokensymbol,
         );
 
         put_token_a(&e, token_a);
         put_token_b(&e, token_b);
-        put_token_share(&e, share_contract.try_into().unwrap());
+        put_token_share(&e, share_contract);
         put_total_shares(&e, 0);
         put_reserve_a(&e, 0);
         put_reserve_b(&e, 0);
         put_k_last(&e, 0);
+        put_admin(&e, admin);
+        if let Some(hooks) = hooks {
+            e.storage().instance().set(&DataKey::Hooks, &hooks);
+        }
     }
 
     fn share_id(e: Env) -> Address {
         get_token_share(&e)
     }
 
-    fn deposit(e: Env, to: Address, desired_a: i128, min_a: i128, desired_b: i128, min_b: i128)->(i128,i128,i128,i128,i128){
+    fn deposit(e: Env, from: Address, to: Address, desired_a: i128, min_a: i128, desired_b: i128, min_b: i128)->(i128,i128,i128,i128,i128){
         // Depositor needs to authorize the deposit
-        to.require_auth();
+        from.require_auth();
+        lock(&e);
+        check_allowed(&e, &from);
+        check_allowed(&e, &to);
 
         let (reserve_a, reserve_b) = (get_reserve_a(&e), get_reserve_b(&e));
 
         // Calculate deposit amounts
         let amounts = get_deposit_amounts(desired_a, min_a, desired_b, min_b, reserve_a, reserve_b);
 
+        if let Some(hooks) = hooks(&e) {
+            hooks.before_deposit(&e.current_contract_address(), &from, &to, &amounts.0, &amounts.1);
+        }
+
         let token_a_client = token::Client::new(&e, &get_token_a(&e));
         let token_b_client = token::Client::new(&e, &get_token_b(&e));
 
-        token_a_client.transfer(&to, &e.current_contract_address(), &amounts.0);
-        token_b_client.transfer(&to, &e.current_contract_address(), &amounts.1);
+        token_a_client.transfer(&from, &e.current_contract_address(), &amounts.0);
+        token_b_client.transfer(&from, &e.current_contract_address(), &amounts.1);
 
         // Now calculate how many new pool shares to mint
         let (balance_a, balance_b) = (get_balance_a(&e), get_balance_b(&e));
@@ -313,16 +815,25 @@ impl LiquidityPoolTrait for LiquidityPool {
             shares - MINIMUN_LIQUIDITY
         };
 
-        mint_shares(&e, to, new_total_shares);
+        record_deposit(&e, to.clone(), amounts.0, amounts.1);
+        mint_shares(&e, to.clone(), new_total_shares);
         put_reserve_a(&e, balance_a);
         put_reserve_b(&e, balance_b);
         put_k_last(&e, balance_a * balance_b);
+
+        if let Some(hooks) = hooks(&e) {
+            hooks.after_deposit(&e.current_contract_address(), &from, &to, &amounts.0, &amounts.1, &new_total_shares);
+        }
+        unlock(&e);
         (new_total_shares,amounts.0,amounts.1,balance_a,balance_b)
     }
 
-    fn swap_exact_input(e: Env, to: Address, swap_x_to_y: bool, x_in: i128,y_min_out: i128)->i128{
+    fn swap_exact_input(e: Env, from: Address, to: Address, swap_x_to_y: bool, x_in: i128,y_min_out: i128,max_price_impact_bps: Option<u32>,referrer: Option<Address>)->i128{
         
-        to.require_auth();
+        from.require_auth();
+        lock(&e);
+        check_allowed(&e, &from);
+        check_allowed(&e, &to);
 
         let (reserve_a, reserve_b) = (get_reserve_a(&e), get_reserve_b(&e));
         let (reserve_x, reserve_y) = if swap_x_to_y {
@@ -332,17 +843,17 @@ impl LiquidityPoolTrait for LiquidityPool {
         };
 
 
-        let amount_out = if swap_x_to_y{
-            get_amount_out(x_in, reserve_x, reserve_y)
-        }else{
-            get_amount_out(x_in, reserve_x, reserve_y)
-        };
+        let fee_bps = get_swap_fee_bps(&e, &from, swap_x_to_y, x_in, true);
+
+        let amount_out = get_amount_out(x_in, reserve_x, reserve_y, fee_bps);
 
 
         if amount_out < y_min_out {
             panic!("Not satisfied minimum out")
         }
 
+        check_price_impact(x_in, amount_out, reserve_x, reserve_y, max_price_impact_bps);
+
         // Transfer the amount being sold to the contract
         let sell_token = if swap_x_to_y {
             get_token_a(&e)
@@ -351,13 +862,13 @@ impl LiquidityPoolTrait for LiquidityPool {
         };
 
         let sell_token_client = token::Client::new(&e, &sell_token);
-        sell_token_client.transfer(&to, &e.current_contract_address(), &x_in);
+        sell_token_client.transfer(&from, &e.current_contract_address(), &x_in);
 
         let (balance_a, balance_b) = (get_balance_a(&e), get_balance_b(&e));
 
         // residue_numerator and residue_denominator are the amount that the invariant considers after
         // deducting the fee, scaled up by 1000 to avoid fractions
-        let residue_numerator = 9975;
+        let residue_numerator = 10000 - fee_bps;
         let residue_denominator = 10000;
         let zero = 0;
 
@@ -386,13 +897,78 @@ impl LiquidityPoolTrait for LiquidityPool {
         }
 
         if swap_x_to_y {
-            transfer_b(&e, to, out_b);
+            transfer_b(&e, to.clone(), out_b);
         } else {
-            transfer_a(&e, to, out_a);
+            transfer_a(&e, to.clone(), out_a);
         }
 
+        let referral = pay_referral(&e, &referrer, sell_token, x_in, fee_bps);
+        let (balance_a, balance_b) = if swap_x_to_y {
+            (balance_a - referral, balance_b)
+        } else {
+            (balance_a, balance_b - referral)
+        };
+
+        check_circuit_breaker(&e, reserve_a, reserve_b, balance_a - out_a, balance_b - out_b);
+        record_volatility(&e, reserve_a, reserve_b, balance_a - out_a, balance_b - out_b);
+        record_swap_stats(&e, swap_x_to_y, x_in, amount_out, fee_bps, referral);
         put_reserve_a(&e, balance_a - out_a);
         put_reserve_b(&e, balance_b - out_b);
+
+        if let Some(hooks) = hooks(&e) {
+            hooks.after_swap(&e.current_contract_address(), &from, &to, &swap_x_to_y, &x_in, &amount_out);
+        }
+        unlock(&e);
+        amount_out
+    }
+
+    fn swap_exact_in_fee_on_transfer(e: Env, from: Address, to: Address, swap_x_to_y: bool, x_in: i128, y_min_out: i128, max_price_impact_bps: Option<u32>, referrer: Option<Address>)->i128{
+
+        from.require_auth();
+        lock(&e);
+        check_allowed(&e, &from);
+        check_allowed(&e, &to);
+
+        let (reserve_a, reserve_b) = (get_reserve_a(&e), get_reserve_b(&e));
+        let (sell_token, reserve_in, reserve_out) = if swap_x_to_y {
+            (get_token_a(&e), reserve_a, reserve_b)
+        } else {
+            (get_token_b(&e), reserve_b, reserve_a)
+        };
+
+        let sell_token_client = token::Client::new(&e, &sell_token);
+        sell_token_client.transfer(&from, &e.current_contract_address(), &x_in);
+
+        // Only what the pool received counts as input
+        let amount_in = get_balance(&e, sell_token.clone()) - reserve_in;
+        let fee_bps = get_swap_fee_bps(&e, &from, swap_x_to_y, amount_in, true);
+        let amount_out = get_amount_out(amount_in, reserve_in, reserve_out, fee_bps);
+
+        if amount_out < y_min_out {
+            panic!("Not satisfied minimum out")
+        }
+
+        check_price_impact(amount_in, amount_out, reserve_in, reserve_out, max_price_impact_bps);
+
+        if swap_x_to_y {
+            transfer_b(&e, to.clone(), amount_out);
+        } else {
+            transfer_a(&e, to.clone(), amount_out);
+        }
+        let referral = pay_referral(&e, &referrer, sell_token, amount_in, fee_bps);
+
+        // Sync to the actual balances, the outgoing transfer may also have been taxed
+        let (balance_a, balance_b) = (get_balance_a(&e), get_balance_b(&e));
+        check_circuit_breaker(&e, reserve_a, reserve_b, balance_a, balance_b);
+        record_volatility(&e, reserve_a, reserve_b, balance_a, balance_b);
+        record_swap_stats(&e, swap_x_to_y, amount_in, amount_out, fee_bps, referral);
+        put_reserve_a(&e, balance_a);
+        put_reserve_b(&e, balance_b);
+
+        if let Some(hooks) = hooks(&e) {
+            hooks.after_swap(&e.current_contract_address(), &from, &to, &swap_x_to_y, &amount_in, &amount_out);
+        }
+        unlock(&e);
         amount_out
     }
 
@@ -401,8 +977,11 @@ impl LiquidityPoolTrait for LiquidityPool {
     // (x + dx) * (y - dy )= k
 
 
-    fn swap_exact_output(e: Env, to: Address, buy_a: bool, y_out: i128, x_max_in: i128)->i128 {
-        to.require_auth();
+    fn swap_exact_output(e: Env, from: Address, to: Address, buy_a: bool, y_out: i128, x_max_in: i128, max_price_impact_bps: Option<u32>, referrer: Option<Address>)->i128 {
+        from.require_auth();
+        lock(&e);
+        check_allowed(&e, &from);
+        check_allowed(&e, &to);
 
         let (reserve_a, reserve_b) = (get_reserve_a(&e), get_reserve_b(&e));
         let (reserve_sell, reserve_buy) = if buy_a {
@@ -411,14 +990,18 @@ impl LiquidityPoolTrait for LiquidityPool {
             (reserve_a, reserve_b)
         };
 
+        let fee_bps = get_swap_fee_bps(&e, &from, !buy_a, y_out, false);
+
         // First calculate how much needs to be sold to buy amount out from the pool
         let n = reserve_sell * y_out * 10000;
-        let d = (reserve_buy - y_out) * 9975;
+        let d = (reserve_buy - y_out) * (10000 - fee_bps);
         let sell_amount = (n / d) + 1;
         if sell_amount > x_max_in {
             panic!("in amount is over max")
         }
 
+        check_price_impact(sell_amount, y_out, reserve_sell, reserve_buy, max_price_impact_bps);
+
         // Transfer the amount being sold to the contract
         let sell_token = if buy_a {
             get_token_b(&e)
@@ -426,13 +1009,13 @@ impl LiquidityPoolTrait for LiquidityPool {
             get_token_a(&e)
         };
         let sell_token_client = token::Client::new(&e, &sell_token);
-        sell_token_client.transfer(&to, &e.current_contract_address(), &sell_amount);
+        sell_token_client.transfer(&from, &e.current_contract_address(), &sell_amount);
 
         let (balance_a, balance_b) = (get_balance_a(&e), get_balance_b(&e));
 
         // residue_numerator and residue_denominator are the amount that the invariant considers after
         // deducting the fee, scaled up by 1000 to avoid fractions
-        let residue_numerator = 9975;
+        let residue_numerator = 10000 - fee_bps;
         let residue_denominator = 10000;
         let zero = 0;
 
@@ -458,58 +1041,174 @@ impl LiquidityPoolTrait for LiquidityPool {
         }
 
         if buy_a {
-            transfer_a(&e, to, out_a);
+            transfer_a(&e, to.clone(), out_a);
         } else {
-            transfer_b(&e, to, out_b);
+            transfer_b(&e, to.clone(), out_b);
         }
 
+        let referral = pay_referral(&e, &referrer, sell_token, sell_amount, fee_bps);
+        let (balance_a, balance_b) = if buy_a {
+            (balance_a, balance_b - referral)
+        } else {
+            (balance_a - referral, balance_b)
+        };
+
+        check_circuit_breaker(&e, reserve_a, reserve_b, balance_a - out_a, balance_b - out_b);
+        record_volatility(&e, reserve_a, reserve_b, balance_a - out_a, balance_b - out_b);
+        record_swap_stats(&e, !buy_a, sell_amount, y_out, fee_bps, referral);
         put_reserve_a(&e, balance_a - out_a);
         put_reserve_b(&e, balance_b - out_b);
 
+        if let Some(hooks) = hooks(&e) {
+            hooks.after_swap(&e.current_contract_address(), &from, &to, &!buy_a, &sell_amount, &y_out);
+        }
+        unlock(&e);
+
         sell_amount
     }
 
 
-    fn withdraw(e: Env, to: Address, liquidity: i128,amount_x_min: i128,amount_y_min: i128)->(i128,i128)  {
-        to.require_auth();
+    fn withdraw(e: Env, from: Address, to: Address, liquidity: i128,amount_x_min: i128,amount_y_min: i128)->(i128,i128)  {
+        from.require_auth();
+        lock(&e);
 
-        // First transfer the pool shares that need to be redeemed
-        let share_token_client = token::Client::new(&e, &get_token_share(&e));
+        let amounts = withdraw_liquidity(&e, from, None, to, liquidity, amount_x_min, amount_y_min);
+        unlock(&e);
 
-        let (balance_a, balance_b) = (get_balance_a(&e), get_balance_b(&e));
+        amounts
+    }
 
-        
+    fn withdraw_from(e: Env, spender: Address, from: Address, to: Address, liquidity: i128,amount_x_min: i128,amount_y_min: i128)->(i128,i128)  {
+        spender.require_auth();
+        lock(&e);
 
-        assert!(share_token_client.balance(&to) >= liquidity,"Don't have a enough liquidity to remove");
+        let amounts = withdraw_liquidity(&e, from, Some(spender), to, liquidity, amount_x_min, amount_y_min);
+        unlock(&e);
 
-        let total_shares = total_lp_supply(&e);
+        amounts
+    }
 
-        // Now calculate the withdraw amounts
-        let out_a = (balance_a * liquidity) / total_shares;
-        let out_b = (balance_b * liquidity) / total_shares;
+    fn get_rsrvs(e: Env) -> (i128, i128) {
+        (get_reserve_a(&e), get_reserve_b(&e))
+    }
 
-        if out_a < amount_x_min || out_b < amount_y_min {
-            panic!("min not satisfied");
-        }
+    fn get_tokens(e: Env) -> (Address, Address) {
+        (get_token_a(&e), get_token_b(&e))
+    }
+
+    fn get_k(e: Env) -> i128 {
+        get_k_last(&e)
+    }
 
+    fn set_circuit_breaker(e: Env, max_move_bps: u32, window_ledgers: u32) {
+        get_admin(&e).require_auth();
+        assert!(window_ledgers > 0, "window must be at least one ledger");
 
-        burn_shares(&e, to.clone(),liquidity);
-        transfer_a(&e, to.clone(), out_a);
-        transfer_b(&e, to, out_b);
-        put_reserve_a(&e, balance_a - out_a);
-        put_reserve_b(&e, balance_b - out_b);
-        put_k_last(&e,get_reserve_a(&e) * get_reserve_a(&e));
+        e.storage().instance().set(&DataKey::BreakerMaxMoveBps, &max_move_bps);
+        e.storage().instance().set(&DataKey::BreakerWindow, &window_ledgers);
+        e.storage().instance().remove(&DataKey::BreakerStartLedger);
+    }
 
-        (out_a, out_b)
-        
+    fn get_circuit_breaker(e: Env) -> (u32, u32) {
+        get_breaker_config(&e)
     }
 
-    fn get_rsrvs(e: Env) -> (i128, i128) {
-        (get_reserve_a(&e), get_reserve_b(&e))
+    fn set_dynamic_fee(e: Env, min_fee_bps: u32, max_fee_bps: u32, slope_bps: u32) {
+        get_admin(&e).require_auth();
+        assert!(min_fee_bps <= max_fee_bps && max_fee_bps < 10000, "invalid fee bounds");
+
+        e.storage().instance().set(&DataKey::DynamicFeeMin, &min_fee_bps);
+        e.storage().instance().set(&DataKey::DynamicFeeMax, &max_fee_bps);
+        e.storage().instance().set(&DataKey::DynamicFeeSlope, &slope_bps);
     }
 
-    fn get_k(e: Env) -> i128 {
-        get_k_last(&e)
+    fn get_fee(e: Env) -> i128 {
+        get_fee_bps(&e)
+    }
+
+    fn quote_swap_fee(e: Env, from: Address, sell_a: bool, amount: i128) -> i128 {
+        get_swap_fee_bps(&e, &from, sell_a, amount, true)
+    }
+
+    fn get_volatility(e: Env) -> i128 {
+        get_volatility(&e)
+    }
+
+    fn get_stats(e: Env) -> PoolStats {
+        get_stats(&e)
+    }
+
+    fn set_referral_share(e: Env, share_bps: u32) {
+        get_admin(&e).require_auth();
+        assert!(share_bps <= MAX_REFERRAL_SHARE_BPS, "referral share above cap");
+
+        e.storage().instance().set(&DataKey::ReferralShareBps, &share_bps);
+    }
+
+    fn get_referral_share(e: Env) -> u32 {
+        get_referral_share_bps(&e)
+    }
+
+    fn get_hooks(e: Env) -> Option<Address> {
+        get_hooks(&e)
+    }
+
+    fn set_permissioned(e: Env, permissioned: bool) {
+        get_admin(&e).require_auth();
+        e.storage().instance().set(&DataKey::Permissioned, &permissioned);
+
+        // The share token only lets allowlisted addresses, and the pool itself, receive shares
+        lptoken::Client::new(&e, &get_token_share(&e)).set_auth_required(&permissioned);
+    }
+
+    fn is_permissioned(e: Env) -> bool {
+        is_permissioned(&e)
+    }
+
+    fn set_allowed(e: Env, id: Address, allowed: bool) {
+        get_admin(&e).require_auth();
+
+        lptoken::Client::new(&e, &get_token_share(&e)).set_authorized(&id, &allowed);
+        if allowed {
+            put_persistent(&e, &UserDataKey::Allowed(id), &true);
+        } else {
+            e.storage().persistent().remove(&UserDataKey::Allowed(id));
+        }
+    }
+
+    fn is_allowed(e: Env, id: Address) -> bool {
+        is_allowed(&e, id)
+    }
+
+    fn get_position(e: Env, id: Address) -> PositionInfo {
+        let position = get_lp_position(&e, id.clone());
+        let shares = get_lp_balance(&e, id);
+        let total_shares = total_lp_supply(&e);
+        let (reserve_a, reserve_b) = (get_reserve_a(&e), get_reserve_b(&e));
+
+        let (value_a, value_b) = if total_shares > 0 {
+            (reserve_a * shares / total_shares, reserve_b * shares / total_shares)
+        } else {
+            (0, 0)
+        };
+
+        let in_b = |amount_a: i128, amount_b: i128| {
+            if reserve_a > 0 {
+                amount_a * reserve_b / reserve_a + amount_b
+            } else {
+                amount_b
+            }
+        };
+
+        PositionInfo {
+            shares,
+            value_a,
+            value_b,
+            deposited_a: position.deposited_a,
+            deposited_b: position.deposited_b,
+            lp_value_in_b: in_b(value_a, value_b),
+            hold_value_in_b: in_b(position.deposited_a, position.deposited_b),
+        }
     }
 
     fn get_lptoken_balance(e: Env,id:Address)->i128{
@@ -1,5 +1,5 @@
 #![allow(unused)]
-use soroban_sdk::{xdr::ToXdr, Address, Bytes, BytesN, Env};
+use soroban_sdk::{token, xdr::ToXdr, Address, Bytes, BytesN, Env, String};
 
 soroban_sdk::contractimport!(
     file = "../../target/wasm32-unknown-unknown/release/soroban_token_contract.wasm"
@@ -19,3 +19,50 @@ pub fn create_contract(
         .with_current_contract(salt)
         .deploy(token_wasm_hash)
 }
+
+const MAX_SYMBOL_LEN: usize = 32;
+
+// Appends the UTF-8 bytes of "s" to buf at *len
+fn append(buf: &mut [u8], len: &mut usize, s: &[u8]) {
+    buf[*len..*len + s.len()].copy_from_slice(s);
+    *len += s.len();
+}
+
+fn append_symbol(buf: &mut [u8], len: &mut usize, symbol: &String) {
+    let symbol_len = symbol.len() as usize;
+    assert!(symbol_len <= MAX_SYMBOL_LEN, "token symbol too long");
+
+    let mut symbol_buf = [0u8; MAX_SYMBOL_LEN];
+    symbol.copy_into_slice(&mut symbol_buf[..symbol_len]);
+    append(buf, len, &symbol_buf[..symbol_len]);
+}
+
+fn to_string(e: &Env, buf: &[u8]) -> String {
+    String::from_slice(e, core::str::from_utf8(buf).expect("token symbol is not valid UTF-8"))
+}
+
+// Derives the share token name and symbol from the underlying tokens' symbols,
+// e.g. "SorobanSwap ETH-USDC LP" and "SSLP-ETH-USDC"
+pub fn lp_token_metadata(e: &Env, token_a: &Address, token_b: &Address) -> (String, String) {
+    let symbol_a = token::Client::new(e, token_a).symbol();
+    let symbol_b = token::Client::new(e, token_b).symbol();
+
+    let mut buf = [0u8; 2 * MAX_SYMBOL_LEN + 16];
+
+    let mut len = 0;
+    append(&mut buf, &mut len, b"SorobanSwap ");
+    append_symbol(&mut buf, &mut len, &symbol_a);
+    append(&mut buf, &mut len, b"-");
+    append_symbol(&mut buf, &mut len, &symbol_b);
+    append(&mut buf, &mut len, b" LP");
+    let name = to_string(e, &buf[..len]);
+
+    let mut len = 0;
+    append(&mut buf, &mut len, b"SSLP-");
+    append_symbol(&mut buf, &mut len, &symbol_a);
+    append(&mut buf, &mut len, b"-");
+    append_symbol(&mut buf, &mut len, &symbol_b);
+    let symbol = to_string(e, &buf[..len]);
+
+    (name, symbol)
+}
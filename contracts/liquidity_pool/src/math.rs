@@ -0,0 +1,37 @@
+// "a" * "b" / "c" for non negative "a", "b" and positive "c", rounded down. The product is kept in 256 bits,
+// so it doesn't overflow for products of 18 decimal reserves. Panics if the result doesn't fit in i128.
+pub fn mul_div(a: i128, b: i128, c: i128) -> i128 {
+    assert!(a >= 0 && b >= 0 && c > 0, "mul_div of negative value");
+    let (a, b, c) = (a as u128, b as u128, c as u128);
+
+    // 128 x 128 bit product as hi * 2^128 + lo, from 64 bit halves
+    let mask = u64::MAX as u128;
+    let (a_hi, a_lo) = (a >> 64, a & mask);
+    let (b_hi, b_lo) = (b >> 64, b & mask);
+
+    let lo_lo = a_lo * b_lo;
+    let cross = (lo_lo >> 64) + ((a_hi * b_lo) & mask) + a_lo * b_hi;
+    let lo = (cross << 64) | (lo_lo & mask);
+    let hi = a_hi * b_hi + ((a_hi * b_lo) >> 64) + (cross >> 64);
+
+    if hi == 0 {
+        return (lo / c) as i128;
+    }
+    assert!(hi < c, "mul_div overflow");
+
+    // Long division, hi < c keeps the quotient within 128 bits
+    let mut rem = hi;
+    let mut quotient = 0u128;
+    for i in (0..128).rev() {
+        let carry = rem >> 127;
+        rem = (rem << 1) | ((lo >> i) & 1);
+        quotient <<= 1;
+        if carry == 1 || rem >= c {
+            rem = rem.wrapping_sub(c);
+            quotient |= 1;
+        }
+    }
+
+    assert!(quotient <= i128::MAX as u128, "mul_div overflow");
+    quotient as i128
+}
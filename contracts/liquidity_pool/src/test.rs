@@ -1,3 +1,948 @@
+#![cfg(test)]
+extern crate std;
+
+use crate::{lptoken, LiquidityPool, LiquidityPoolClient, PoolStats, PositionInfo};
+
+use std::rc::Rc;
+
+use soroban_sdk::{
+    contract, contractimpl, symbol_short,
+    testutils::{Address as _, AuthorizedFunction, AuthorizedInvocation, BytesN as _, Ledger},
+    xdr::{
+        AccountEntry, AccountEntryExt, AccountId, LedgerEntry, LedgerEntryData, LedgerEntryExt, LedgerKey,
+        LedgerKeyAccount, PublicKey, ScAddress, SequenceNumber, Thresholds, Uint256,
+    },
+    Address, BytesN, Env, IntoVal, String, Symbol, TryFromVal,
+};
+
+// Token that calls back into the pool from inside `transfer`, recording whether the pool let it in
+#[contract]
+pub struct HostileToken;
+
+#[contractimpl]
+impl HostileToken {
+    pub fn set_target(e: Env, pool: Address) {
+        e.storage().instance().set(&symbol_short!("target"), &pool);
+    }
+
+    pub fn mint(e: Env, to: Address, amount: i128) {
+        let balance = Self::balance(e.clone(), to.clone());
+        e.storage().instance().set(&to, &(balance + amount));
+    }
+
+    pub fn balance(e: Env, id: Address) -> i128 {
+        e.storage().instance().get(&id).unwrap_or(0)
+    }
+
+    pub fn transfer(e: Env, from: Address, to: Address, amount: i128) {
+        let from_balance = Self::balance(e.clone(), from.clone());
+        let to_balance = Self::balance(e.clone(), to.clone());
+        e.storage().instance().set(&from, &(from_balance - amount));
+        e.storage().instance().set(&to, &(to_balance + amount));
+
+        let target: Option<Address> = e.storage().instance().get(&symbol_short!("target"));
+        if let Some(pool) = target {
+            e.storage().instance().remove(&symbol_short!("target"));
+            let reentered = LiquidityPoolClient::new(&e, &pool)
+                .try_swap_exact_input(&from, &from, &true, &1000, &0, &None, &None)
+                .is_ok();
+            e.storage().instance().set(&symbol_short!("reentered"), &reentered);
+        }
+    }
+
+    pub fn reentered(e: Env) -> bool {
+        e.storage().instance().get(&symbol_short!("reentered")).unwrap_or(false)
+    }
+}
+
+// Hooks that charge a flat 1% fee and veto deposits of more than 1_000_000 of token_a
+#[contract]
+pub struct FeeHooks;
+
+#[contractimpl]
+impl FeeHooks {
+    pub fn before_deposit(_e: Env, _pool: Address, _from: Address, _to: Address, amount_a: i128, _amount_b: i128) {
+        if amount_a > 1_000_000 {
+            panic!("deposit too large");
+        }
+    }
+
+    pub fn after_deposit(_e: Env, _pool: Address, _from: Address, _to: Address, _amount_a: i128, _amount_b: i128, _shares: i128) {}
+
+    pub fn before_swap(_e: Env, _pool: Address, _from: Address, _sell_a: bool, _amount: i128, _exact_input: bool, _fee_bps: i128) -> i128 {
+        100
+    }
+
+    pub fn after_swap(e: Env, _pool: Address, _from: Address, _to: Address, _sell_a: bool, _amount_in: i128, amount_out: i128) {
+        e.storage().instance().set(&symbol_short!("last_out"), &amount_out);
+    }
+
+    pub fn before_withdraw(_e: Env, _pool: Address, _from: Address, _to: Address, _shares: i128) {}
+
+    pub fn after_withdraw(_e: Env, _pool: Address, _from: Address, _to: Address, _shares: i128, _amount_a: i128, _amount_b: i128) {}
+
+    pub fn last_out(e: Env) -> i128 {
+        e.storage().instance().get(&symbol_short!("last_out")).unwrap_or(0)
+    }
+}
+
+// Token that burns 1% of every transfer, standing in for community tokens with a transfer tax. In its
+// own module because contract functions share a namespace with HostileToken's.
+mod taxed_token {
+    use soroban_sdk::{contract, contractimpl, Address, Env};
+
+    #[contract]
+    pub struct TaxedToken;
+
+    #[contractimpl]
+    impl TaxedToken {
+        pub fn mint(e: Env, to: Address, amount: i128) {
+            let balance = Self::balance(e.clone(), to.clone());
+            e.storage().instance().set(&to, &(balance + amount));
+        }
+
+        pub fn balance(e: Env, id: Address) -> i128 {
+            e.storage().instance().get(&id).unwrap_or(0)
+        }
+
+        pub fn transfer(e: Env, from: Address, to: Address, amount: i128) {
+            from.require_auth();
+            let from_balance = Self::balance(e.clone(), from.clone());
+            let to_balance = Self::balance(e.clone(), to.clone());
+            assert!(from_balance >= amount, "insufficient balance");
+            e.storage().instance().set(&from, &(from_balance - amount));
+            e.storage().instance().set(&to, &(to_balance + amount - amount / 100));
+        }
+    }
+}
+use taxed_token::{TaxedToken, TaxedTokenClient};
+
+fn create_token_contract<'a>(e: &Env, admin: &Address) -> lptoken::Client<'a> {
+    lptoken::Client::new(e, &e.register_stellar_asset_contract(admin.clone()))
+}
+
+fn create_hostile_token_contract<'a>(e: &Env) -> HostileTokenClient<'a> {
+    HostileTokenClient::new(e, &e.register_contract(None, HostileToken {}))
+}
+
+fn create_liqpool_contract<'a>(
+    e: &Env,
+    token_wasm_hash: &BytesN<32>,
+    token_a: &Address,
+    token_b: &Address,
+    hooks: &Option<Address>,
+) -> LiquidityPoolClient<'a> {
+    create_liqpool_contract_with_admin(e, token_wasm_hash, token_a, token_b, hooks, &Address::random(e))
+}
+
+fn create_liqpool_contract_with_admin<'a>(
+    e: &Env,
+    token_wasm_hash: &BytesN<32>,
+    token_a: &Address,
+    token_b: &Address,
+    hooks: &Option<Address>,
+    admin: &Address,
+) -> LiquidityPoolClient<'a> {
+    let liqpool = LiquidityPoolClient::new(e, &e.register_contract(None, LiquidityPool {}));
+    liqpool.initialize(
+        token_wasm_hash,
+        token_a,
+        token_b,
+        &Some(String::from_slice(e, "Pool Share")),
+        &Some(String::from_slice(e, "POOL")),
+        admin,
+        hooks,
+    );
+    liqpool
+}
+
+fn install_token_wasm(e: &Env) -> BytesN<32> {
+    e.deployer().upload_contract_wasm(lptoken::WASM)
+}
+
+fn setup<'a>(e: &Env) -> (HostileTokenClient<'a>, lptoken::Client<'a>, LiquidityPoolClient<'a>, Address) {
+    let admin = Address::random(e);
+    let user = Address::random(e);
+
+    let hostile = create_hostile_token_contract(e);
+    let token = create_token_contract(e, &admin);
+    let liqpool = create_liqpool_contract(e, &install_token_wasm(e), &hostile.address, &token.address, &None);
+
+    hostile.mint(&user, &10_000_000);
+    token.mint(&user, &10_000_000);
+
+    (hostile, token, liqpool, user)
+}
+
+// These two show that the host rejects a hostile token's re-entry. The host refuses it before the pool
+// runs, so they pass with or without the pool's lock and don't test it.
+#[test]
+fn test_reentrant_deposit_is_rejected() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let (hostile, _token, liqpool, user) = setup(&e);
+
+    hostile.set_target(&liqpool.address);
+    liqpool.deposit(&user, &user, &1_000_000, &0, &1_000_000, &0);
+
+    assert!(!hostile.reentered());
+    assert_eq!(liqpool.get_rsrvs(), (1_000_000, 1_000_000));
+}
+
+#[test]
+fn test_reentrant_swap_is_rejected() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let (hostile, _token, liqpool, user) = setup(&e);
+    liqpool.deposit(&user, &user, &1_000_000, &0, &1_000_000, &0);
+
+    hostile.set_target(&liqpool.address);
+    let out = liqpool.swap_exact_input(&user, &user, &true, &10_000, &0, &None, &None);
+
+    assert!(!hostile.reentered());
+    assert_eq!(liqpool.get_rsrvs(), (1_010_000, 1_000_000 - out));
+}
+
+// Deploys a funded pool and takes its lock as an in-flight entrypoint would. While the host forbids
+// re-entry no call can reach a taken lock, so the locked_* tests take it directly.
+fn setup_locked<'a>(e: &Env) -> (LiquidityPoolClient<'a>, Address) {
+    let admin = Address::random(e);
+    let user = Address::random(e);
+
+    let token_a = create_token_contract(e, &admin);
+    let token_b = create_token_contract(e, &admin);
+    let liqpool = create_liqpool_contract(e, &install_token_wasm(e), &token_a.address, &token_b.address, &None);
+    token_a.mint(&user, &10_000_000);
+    token_b.mint(&user, &10_000_000);
+    liqpool.deposit(&user, &user, &1_000_000, &0, &1_000_000, &0);
+
+    e.as_contract(&liqpool.address, || crate::lock(e));
+    (liqpool, user)
+}
+
+#[test]
+fn test_lock_is_released() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let (liqpool, user) = setup_locked(&e);
+    e.as_contract(&liqpool.address, || crate::unlock(&e));
+
+    liqpool.swap_exact_input(&user, &user, &true, &10_000, &0, &None, &None);
+    liqpool.withdraw(&user, &user, &1_000, &0, &0);
+}
+
+#[test]
+#[should_panic(expected = "Reentrant call")]
+fn test_locked_deposit() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let (liqpool, user) = setup_locked(&e);
+    liqpool.deposit(&user, &user, &1_000_000, &0, &1_000_000, &0);
+}
+
+#[test]
+#[should_panic(expected = "Reentrant call")]
+fn test_locked_swap() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let (liqpool, user) = setup_locked(&e);
+    liqpool.swap_exact_input(&user, &user, &true, &10_000, &0, &None, &None);
+}
+
+#[test]
+#[should_panic(expected = "Reentrant call")]
+fn test_locked_withdraw() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let (liqpool, user) = setup_locked(&e);
+    liqpool.withdraw(&user, &user, &1_000, &0, &0);
+}
+
+#[test]
+fn test_swap_fee_on_transfer_token() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let user = Address::random(&e);
+
+    let taxed = TaxedTokenClient::new(&e, &e.register_contract(None, TaxedToken {}));
+    let token = create_token_contract(&e, &admin);
+    let liqpool = create_liqpool_contract(&e, &install_token_wasm(&e), &taxed.address, &token.address, &None);
+
+    taxed.mint(&user, &10_000_000);
+    token.mint(&user, &10_000_000);
+
+    // The pool is credited with what arrived after the tax
+    liqpool.deposit(&user, &user, &1_000_000, &0, &1_000_000, &0);
+    assert_eq!(liqpool.get_rsrvs(), (990_000, 1_000_000));
+
+    // Selling the taxed token is quoted from the 9_900 the pool received
+    let out = liqpool.swap_exact_in_fee_on_transfer(&user, &user, &true, &10_000, &0, &None, &None);
+    assert_eq!(out, crate::get_amount_out(9_900, 990_000, 1_000_000, 25));
+    assert_eq!(liqpool.get_rsrvs(), (999_900, 1_000_000 - out));
+    assert_eq!(token.balance(&user), 9_000_000 + out);
+
+    // Buying it, the reserves follow the pool's balance after the taxed outgoing transfer
+    let taxed_before = taxed.balance(&user);
+    let taxed_out = liqpool.swap_exact_in_fee_on_transfer(&user, &user, &false, &10_000, &0, &None, &None);
+    assert_eq!(taxed.balance(&user), taxed_before + taxed_out - taxed_out / 100);
+    assert_eq!(liqpool.get_rsrvs(), (999_900 - taxed_out, 1_010_000 - out));
+}
+
+#[test]
+#[should_panic(expected = "constant product invariant does not hold")]
+fn test_swap_exact_input_rejects_fee_on_transfer_token() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let user = Address::random(&e);
+
+    let taxed = TaxedTokenClient::new(&e, &e.register_contract(None, TaxedToken {}));
+    let token = create_token_contract(&e, &admin);
+    let liqpool = create_liqpool_contract(&e, &install_token_wasm(&e), &taxed.address, &token.address, &None);
+
+    taxed.mint(&user, &10_000_000);
+    token.mint(&user, &10_000_000);
+    liqpool.deposit(&user, &user, &1_000_000, &0, &1_000_000, &0);
+
+    liqpool.swap_exact_input(&user, &user, &true, &10_000, &0, &None, &None);
+}
+
+#[test]
+fn test_payer_and_recipient_are_separate() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let payer = Address::random(&e);
+    let holder = Address::random(&e);
+    let recipient = Address::random(&e);
+
+    let token_a = create_token_contract(&e, &admin);
+    let token_b = create_token_contract(&e, &admin);
+    let liqpool = create_liqpool_contract(&e, &install_token_wasm(&e), &token_a.address, &token_b.address, &None);
+    token_a.mint(&payer, &10_000_000);
+    token_b.mint(&payer, &10_000_000);
+
+    // Only the payer signs and pays, the shares go to the holder
+    let (shares, _, _, _, _) = liqpool.deposit(&payer, &holder, &1_000_000, &0, &1_000_000, &0);
+    assert_eq!(e.auths().len(), 1);
+    assert_eq!(e.auths()[0].0, payer);
+    assert_eq!(token_a.balance(&payer), 9_000_000);
+    assert_eq!(liqpool.get_lptoken_balance(&holder), shares);
+    assert_eq!(liqpool.get_lptoken_balance(&payer), 0);
+
+    let out = liqpool.swap_exact_input(&payer, &recipient, &true, &10_000, &0, &None, &None);
+    assert_eq!(e.auths().len(), 1);
+    assert_eq!(e.auths()[0].0, payer);
+    assert_eq!(token_a.balance(&payer), 8_990_000);
+    assert_eq!(token_b.balance(&payer), 9_000_000);
+    assert_eq!(token_b.balance(&recipient), out);
+
+    let sold = liqpool.swap_exact_output(&payer, &recipient, &true, &5_000, &10_000, &None, &None);
+    assert_eq!(e.auths().len(), 1);
+    assert_eq!(e.auths()[0].0, payer);
+    assert_eq!(token_b.balance(&payer), 9_000_000 - sold);
+    assert_eq!(token_a.balance(&recipient), 5_000);
+
+    // Withdrawing burns the holder's shares and pays the recipient
+    let (amount_a, amount_b) = liqpool.withdraw(&holder, &recipient, &shares, &0, &0);
+    assert_eq!(e.auths().len(), 1);
+    assert_eq!(e.auths()[0].0, holder);
+    assert_eq!(liqpool.get_lptoken_balance(&holder), 0);
+    assert_eq!(token_a.balance(&recipient), 5_000 + amount_a);
+    assert_eq!(token_b.balance(&recipient), out + amount_b);
+    assert_eq!(token_a.balance(&holder), 0);
+}
+
+// Price impact in basis points of selling "amount_in" for "amount_out", the way check_price_impact measures it
+fn price_impact_bps(amount_in: i128, amount_out: i128, reserve_in: i128, reserve_out: i128) -> u32 {
+    (10000 - (reserve_out - amount_out) * reserve_in * 10000 / (reserve_out * (reserve_in + amount_in))) as u32
+}
+
+#[test]
+fn test_price_impact_within_cap() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let user = Address::random(&e);
+
+    let token_a = create_token_contract(&e, &admin);
+    let token_b = create_token_contract(&e, &admin);
+    let liqpool = create_liqpool_contract(&e, &install_token_wasm(&e), &token_a.address, &token_b.address, &None);
+    token_a.mint(&user, &10_000_000);
+    token_b.mint(&user, &10_000_000);
+    liqpool.deposit(&user, &user, &1_000_000, &0, &1_000_000, &0);
+
+    // Exactly at the cap is accepted
+    let out = crate::get_amount_out(10_000, 1_000_000, 1_000_000, 25);
+    let impact = price_impact_bps(10_000, out, 1_000_000, 1_000_000);
+    assert!(impact > 190 && impact < 200);
+    assert_eq!(liqpool.swap_exact_input(&user, &user, &true, &10_000, &0, &Some(impact), &None), out);
+
+    // Buying back a small amount of the now cheaper token_a barely moves the price
+    let sold = liqpool.swap_exact_output(&user, &user, &true, &1_000, &2_000, &Some(25), &None);
+    assert!(sold > 0 && sold < 1_000);
+    assert_eq!(token_a.balance(&user), 8_991_000);
+}
+
+#[test]
+#[should_panic(expected = "price impact too high")]
+fn test_price_impact_above_cap() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let user = Address::random(&e);
+
+    let token_a = create_token_contract(&e, &admin);
+    let token_b = create_token_contract(&e, &admin);
+    let liqpool = create_liqpool_contract(&e, &install_token_wasm(&e), &token_a.address, &token_b.address, &None);
+    token_a.mint(&user, &10_000_000);
+    token_b.mint(&user, &10_000_000);
+    liqpool.deposit(&user, &user, &1_000_000, &0, &1_000_000, &0);
+
+    let out = crate::get_amount_out(10_000, 1_000_000, 1_000_000, 25);
+    let impact = price_impact_bps(10_000, out, 1_000_000, 1_000_000);
+    liqpool.swap_exact_input(&user, &user, &true, &10_000, &0, &Some(impact - 1), &None);
+}
+
+// Pool of 1_000_000 of each token with the circuit breaker set by its admin
+fn setup_breaker<'a>(e: &Env, max_move_bps: u32, window_ledgers: u32) -> (LiquidityPoolClient<'a>, Address) {
+    let admin = Address::random(e);
+    let user = Address::random(e);
+
+    let token_a = create_token_contract(e, &admin);
+    let token_b = create_token_contract(e, &admin);
+    let liqpool = create_liqpool_contract_with_admin(e, &install_token_wasm(e), &token_a.address, &token_b.address, &None, &admin);
+    token_a.mint(&user, &10_000_000);
+    token_b.mint(&user, &10_000_000);
+    liqpool.deposit(&user, &user, &1_000_000, &0, &1_000_000, &0);
+
+    liqpool.set_circuit_breaker(&max_move_bps, &window_ledgers);
+    assert_eq!(
+        e.auths(),
+        std::vec![(
+            admin,
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    liqpool.address.clone(),
+                    Symbol::new(e, "set_circuit_breaker"),
+                    (max_move_bps, window_ledgers).into_val(e),
+                )),
+                sub_invocations: std::vec![]
+            }
+        )]
+    );
+    assert_eq!(liqpool.get_circuit_breaker(), (max_move_bps, window_ledgers));
+
+    (liqpool, user)
+}
+
+#[test]
+fn test_circuit_breaker_window_resets() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    // Each 3_000 swap moves the price by about 0.6%, two of them by about 1.2%
+    let (liqpool, user) = setup_breaker(&e, 100, 10);
+    liqpool.swap_exact_input(&user, &user, &true, &3_000, &0, &None, &None);
+    assert!(liqpool.try_swap_exact_input(&user, &user, &true, &3_000, &0, &None, &None).is_err());
+
+    // Swapping back within the window is fine, the price returns towards the start
+    liqpool.swap_exact_input(&user, &user, &false, &3_000, &0, &None, &None);
+
+    e.ledger().with_mut(|li| li.sequence_number += 10);
+    liqpool.swap_exact_input(&user, &user, &true, &3_000, &0, &None, &None);
+    e.ledger().with_mut(|li| li.sequence_number += 10);
+    liqpool.swap_exact_input(&user, &user, &true, &3_000, &0, &None, &None);
+
+    // Withdrawals stay open while swaps are halted
+    assert!(liqpool.try_swap_exact_input(&user, &user, &true, &3_000, &0, &None, &None).is_err());
+    liqpool.withdraw(&user, &user, &100_000, &0, &0);
+}
+
+#[test]
+#[should_panic(expected = "circuit breaker: price moved too much in this window")]
+fn test_circuit_breaker_trips() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let (liqpool, user) = setup_breaker(&e, 100, 10);
+    liqpool.swap_exact_input(&user, &user, &true, &6_000, &0, &None, &None);
+}
+
+#[test]
+fn test_circuit_breaker_with_unbounded_window() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    // 1_000 + u32::MAX saturates instead of overflowing, so later swaps stay in the first window
+    let (liqpool, user) = setup_breaker(&e, 100, u32::MAX);
+    e.ledger().with_mut(|li| li.sequence_number = 1_000);
+    liqpool.swap_exact_input(&user, &user, &true, &3_000, &0, &None, &None);
+    liqpool.swap_exact_input(&user, &user, &false, &3_000, &0, &None, &None);
+}
+
+#[test]
+fn test_circuit_breaker_with_18_decimal_reserves() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    // A billion tokens with 18 decimals on each side, their product alone is far above i128::MAX
+    let (liqpool, _) = setup_breaker(&e, 100, 10);
+    let reserve = 1_000_000_000 * 10i128.pow(18);
+    e.as_contract(&liqpool.address, || {
+        crate::check_circuit_breaker(&e, reserve, reserve, reserve + reserve / 200, reserve - reserve / 201);
+    });
+}
+
+#[test]
+#[should_panic(expected = "circuit breaker: price moved too much in this window")]
+fn test_circuit_breaker_trips_with_18_decimal_reserves() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let (liqpool, _) = setup_breaker(&e, 100, 10);
+    let reserve = 1_000_000_000 * 10i128.pow(18);
+    e.as_contract(&liqpool.address, || {
+        crate::check_circuit_breaker(&e, reserve, reserve, reserve + reserve / 100, reserve - reserve / 101);
+    });
+}
+
+#[test]
+fn test_dynamic_fee_rises_and_decays_over_time() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let user = Address::random(&e);
+
+    let token_a = create_token_contract(&e, &admin);
+    let token_b = create_token_contract(&e, &admin);
+    let liqpool = create_liqpool_contract_with_admin(&e, &install_token_wasm(&e), &token_a.address, &token_b.address, &None, &admin);
+    token_a.mint(&user, &10_000_000);
+    token_b.mint(&user, &10_000_000);
+    liqpool.deposit(&user, &user, &1_000_000, &0, &1_000_000, &0);
+
+    liqpool.set_dynamic_fee(&10, &300, &1_000);
+    assert_eq!(liqpool.get_fee(), 10);
+
+    // Selling 50_000 into 1_000_000 moves the price by about 9.3%
+    liqpool.swap_exact_input(&user, &user, &true, &50_000, &0, &None, &None);
+    let volatility = liqpool.get_volatility();
+    assert!(volatility > 900 && volatility < 1_000);
+    assert_eq!(liqpool.get_fee(), 10 + volatility / 10);
+
+    // Dust swaps barely move the price and don't wear the volatility down
+    for i in 0..20 {
+        liqpool.swap_exact_input(&user, &user, &(i % 2 == 0), &10, &0, &None, &None);
+    }
+    let volatility = liqpool.get_volatility();
+    assert!(volatility > 900);
+
+    // Halves over a half-life, linearly in between
+    e.ledger().with_mut(|li| li.timestamp += 600);
+    assert_eq!(liqpool.get_volatility(), volatility / 2);
+    e.ledger().with_mut(|li| li.timestamp += 300);
+    let half = volatility / 2;
+    assert_eq!(liqpool.get_volatility(), half - half * 300 / 1_200);
+
+    // Back to the minimum fee once it has fully decayed
+    e.ledger().with_mut(|li| li.timestamp += 600 * 64);
+    assert_eq!(liqpool.get_volatility(), 0);
+    assert_eq!(liqpool.get_fee(), 10);
+}
+
+#[test]
+fn test_volatility_with_18_decimal_reserves() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let token_a = create_token_contract(&e, &admin);
+    let token_b = create_token_contract(&e, &admin);
+    let liqpool = create_liqpool_contract_with_admin(&e, &install_token_wasm(&e), &token_a.address, &token_b.address, &None, &admin);
+    liqpool.set_dynamic_fee(&10, &300, &1_000);
+
+    // A billion tokens with 18 decimals on each side, moved by about 2%
+    let reserve = 1_000_000_000 * 10i128.pow(18);
+    e.as_contract(&liqpool.address, || {
+        crate::record_volatility(&e, reserve, reserve, reserve + reserve / 100, reserve - reserve / 101);
+    });
+    assert_eq!(liqpool.get_volatility(), 197);
+}
+
+#[test]
+fn test_referrer_is_paid_share_of_fee() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let user = Address::random(&e);
+    let referrer = Address::random(&e);
+
+    let token_a = create_token_contract(&e, &admin);
+    let token_b = create_token_contract(&e, &admin);
+    let liqpool = create_liqpool_contract(&e, &install_token_wasm(&e), &token_a.address, &token_b.address, &None);
+
+    token_a.mint(&user, &10_000_000);
+    token_b.mint(&user, &10_000_000);
+    liqpool.deposit(&user, &user, &1_000_000, &0, &1_000_000, &0);
+
+    liqpool.set_referral_share(&2000);
+    assert_eq!(liqpool.get_referral_share(), 2000);
+
+    // 0.25% fee on 100_000 is 250, 20% of it goes to the referrer
+    let out = liqpool.swap_exact_input(&user, &user, &true, &100_000, &0, &None, &Some(referrer.clone()));
+
+    assert_eq!(token_a.balance(&referrer), 50);
+    assert_eq!(liqpool.get_rsrvs(), (1_100_000 - 50, 1_000_000 - out));
+}
+
+#[test]
+fn test_stats_track_volume_and_fees_net_of_referrals() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let user = Address::random(&e);
+    let referrer = Address::random(&e);
+
+    let token_a = create_token_contract(&e, &admin);
+    let token_b = create_token_contract(&e, &admin);
+    let liqpool = create_liqpool_contract(&e, &install_token_wasm(&e), &token_a.address, &token_b.address, &None);
+
+    token_a.mint(&user, &10_000_000);
+    token_b.mint(&user, &10_000_000);
+    liqpool.deposit(&user, &user, &1_000_000, &0, &1_000_000, &0);
+    liqpool.set_referral_share(&2000);
+
+    // 0.25% of 100_000 without a referrer
+    let out_b = liqpool.swap_exact_input(&user, &user, &true, &100_000, &0, &None, &None);
+    // 0.25% of 40_000 is 100, of which 20 goes to the referrer
+    let out_a = liqpool.swap_exact_input(&user, &user, &false, &40_000, &0, &None, &Some(referrer.clone()));
+    let paid_b = liqpool.swap_exact_output(&user, &user, &true, &10_000, &i128::MAX, &None, &Some(referrer.clone()));
+    let fee = paid_b * 25 / 10000;
+
+    assert_eq!(
+        liqpool.get_stats(),
+        PoolStats {
+            volume_in_a: 100_000,
+            volume_in_b: 40_000 + paid_b,
+            volume_out_a: out_a + 10_000,
+            volume_out_b: out_b,
+            fees_a: 250,
+            fees_b: 80 + fee - fee * 2000 / 10000,
+        }
+    );
+    assert_eq!(token_b.balance(&referrer), 20 + fee * 2000 / 10000);
+}
+
+#[test]
+fn test_position_tracks_cost_basis() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let user = Address::random(&e);
+    let trader = Address::random(&e);
+    let stranger = Address::random(&e);
+
+    let token_a = create_token_contract(&e, &admin);
+    let token_b = create_token_contract(&e, &admin);
+    let liqpool = create_liqpool_contract(&e, &install_token_wasm(&e), &token_a.address, &token_b.address, &None);
+
+    token_a.mint(&user, &10_000_000);
+    token_b.mint(&user, &10_000_000);
+    token_a.mint(&trader, &10_000_000);
+    liqpool.deposit(&user, &user, &1_000_000, &0, &1_000_000, &0);
+
+    // The minimum liquidity is locked in the pool, the rest of the shares go to the user
+    let shares = 1_000_000 - 1000;
+    assert_eq!(
+        liqpool.get_position(&user),
+        PositionInfo {
+            shares,
+            value_a: shares,
+            value_b: shares,
+            deposited_a: 1_000_000,
+            deposited_b: 1_000_000,
+            lp_value_in_b: 2 * shares,
+            hold_value_in_b: 2_000_000,
+        }
+    );
+
+    // After a price move holding is worth more than the position, the difference is the impermanent loss
+    liqpool.swap_exact_input(&trader, &trader, &true, &100_000, &0, &None, &None);
+    let (reserve_a, reserve_b) = liqpool.get_rsrvs();
+    let position = liqpool.get_position(&user);
+    assert_eq!(position.value_a, reserve_a * shares / 1_000_000);
+    assert_eq!(position.value_b, reserve_b * shares / 1_000_000);
+    assert_eq!(position.hold_value_in_b, 1_000_000 * reserve_b / reserve_a + 1_000_000);
+    assert!(position.lp_value_in_b < position.hold_value_in_b);
+
+    // Withdrawing half the shares releases half the cost basis
+    liqpool.withdraw(&user, &user, &(shares / 2), &0, &0);
+    let position = liqpool.get_position(&user);
+    assert_eq!(position.shares, shares - shares / 2);
+    assert_eq!((position.deposited_a, position.deposited_b), (500_000, 500_000));
+
+    // Nothing to release for an address without shares
+    liqpool.withdraw(&stranger, &stranger, &0, &0, &0);
+    let position = liqpool.get_position(&stranger);
+    assert_eq!((position.shares, position.deposited_a, position.deposited_b), (0, 0, 0));
+}
+
+#[test]
+fn test_withdraw_from_spends_allowance() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let user = Address::random(&e);
+    let spender = Address::random(&e);
+    let recipient = Address::random(&e);
+
+    let token_a = create_token_contract(&e, &admin);
+    let token_b = create_token_contract(&e, &admin);
+    let liqpool = create_liqpool_contract(&e, &install_token_wasm(&e), &token_a.address, &token_b.address, &None);
+
+    token_a.mint(&user, &10_000_000);
+    token_b.mint(&user, &10_000_000);
+    liqpool.deposit(&user, &user, &1_000_000, &0, &1_000_000, &0);
+
+    let share = lptoken::Client::new(&e, &liqpool.share_id());
+    share.approve(&user, &spender, &500_000, &1000);
+
+    // The spender authorizes and the shares come out of the owner's balance and the allowance
+    assert_eq!(liqpool.withdraw_from(&spender, &user, &recipient, &400_000, &0, &0), (400_000, 400_000));
+    assert_eq!(
+        e.auths(),
+        std::vec![(
+            spender.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    liqpool.address.clone(),
+                    Symbol::new(&e, "withdraw_from"),
+                    (&spender, &user, &recipient, 400_000_i128, 0_i128, 0_i128).into_val(&e),
+                )),
+                sub_invocations: std::vec![AuthorizedInvocation {
+                    function: AuthorizedFunction::Contract((
+                        share.address.clone(),
+                        Symbol::new(&e, "burn_from"),
+                        (&spender, &user, 400_000_i128).into_val(&e),
+                    )),
+                    sub_invocations: std::vec![]
+                }]
+            }
+        )]
+    );
+
+    assert_eq!(token_a.balance(&recipient), 400_000);
+    assert_eq!(token_b.balance(&recipient), 400_000);
+    assert_eq!(share.balance(&user), 999_000 - 400_000);
+    assert_eq!(share.allowance(&user, &spender), 100_000);
+    assert_eq!(liqpool.get_rsrvs(), (600_000, 600_000));
+}
+
+#[test]
+fn test_withdraw_from_fails_above_allowance() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let user = Address::random(&e);
+    let spender = Address::random(&e);
+
+    let token_a = create_token_contract(&e, &admin);
+    let token_b = create_token_contract(&e, &admin);
+    let liqpool = create_liqpool_contract(&e, &install_token_wasm(&e), &token_a.address, &token_b.address, &None);
+
+    token_a.mint(&user, &10_000_000);
+    token_b.mint(&user, &10_000_000);
+    liqpool.deposit(&user, &user, &1_000_000, &0, &1_000_000, &0);
+
+    let share = lptoken::Client::new(&e, &liqpool.share_id());
+    share.approve(&user, &spender, &100_000, &1000);
+
+    assert!(liqpool.try_withdraw_from(&spender, &user, &spender, &100_001, &0, &0).is_err());
+    assert!(liqpool.try_withdraw_from(&user, &spender, &user, &1, &0, &0).is_err());
+    assert_eq!(share.balance(&user), 999_000);
+    assert_eq!(liqpool.get_rsrvs(), (1_000_000, 1_000_000));
+}
+
+// Classic account, the only kind a permissioned pool allows. The host reads its entry when it signs.
+fn create_account(e: &Env) -> Address {
+    let account_id = AccountId(PublicKey::PublicKeyTypeEd25519(Uint256(BytesN::<32>::random(e).to_array())));
+    let key = LedgerKey::Account(LedgerKeyAccount { account_id: account_id.clone() });
+    let entry = LedgerEntry {
+        last_modified_ledger_seq: 0,
+        data: LedgerEntryData::Account(AccountEntry {
+            account_id: account_id.clone(),
+            balance: 0,
+            seq_num: SequenceNumber(0),
+            num_sub_entries: 0,
+            inflation_dest: None,
+            flags: 0,
+            home_domain: Default::default(),
+            thresholds: Thresholds([1, 0, 0, 0]),
+            signers: Default::default(),
+            ext: AccountEntryExt::V0,
+        }),
+        ext: LedgerEntryExt::V0,
+    };
+    e.host().add_ledger_entry(&Rc::new(key), &Rc::new(entry), None).unwrap();
+
+    Address::try_from_val(e, &ScAddress::Account(account_id)).unwrap()
+}
+
+fn create_named_token<'a>(e: &Env, admin: &Address, symbol: &str) -> lptoken::Client<'a> {
+    let token = lptoken::Client::new(e, &e.register_contract_wasm(None, lptoken::WASM));
+    token.initialize(admin, &7, &String::from_slice(e, symbol), &String::from_slice(e, symbol));
+    token
+}
+
+#[test]
+fn test_default_lp_token_metadata() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let eth = create_named_token(&e, &admin, "ETH");
+    let usdc = create_named_token(&e, &admin, "USDC");
+
+    let liqpool = LiquidityPoolClient::new(&e, &e.register_contract(None, LiquidityPool {}));
+    liqpool.initialize(&install_token_wasm(&e), &eth.address, &usdc.address, &None, &None, &admin, &None);
+    let share = lptoken::Client::new(&e, &liqpool.share_id());
+    assert_eq!(share.name(), String::from_slice(&e, "SorobanSwap ETH-USDC LP"));
+    assert_eq!(share.symbol(), String::from_slice(&e, "SSLP-ETH-USDC"));
+
+    // Only the missing half is derived
+    let liqpool = LiquidityPoolClient::new(&e, &e.register_contract(None, LiquidityPool {}));
+    liqpool.initialize(&install_token_wasm(&e), &usdc.address, &eth.address, &Some(String::from_slice(&e, "Stable Pool")), &None, &admin, &None);
+    let share = lptoken::Client::new(&e, &liqpool.share_id());
+    assert_eq!(share.name(), String::from_slice(&e, "Stable Pool"));
+    assert_eq!(share.symbol(), String::from_slice(&e, "SSLP-USDC-ETH"));
+}
+
+#[test]
+fn test_hooks_adjust_fee_and_veto() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let user = Address::random(&e);
+
+    let hooks = FeeHooksClient::new(&e, &e.register_contract(None, FeeHooks {}));
+    let token_a = create_token_contract(&e, &admin);
+    let token_b = create_token_contract(&e, &admin);
+    let liqpool = create_liqpool_contract(&e, &install_token_wasm(&e), &token_a.address, &token_b.address, &Some(hooks.address.clone()));
+    assert_eq!(liqpool.get_hooks(), Some(hooks.address.clone()));
+
+    token_a.mint(&user, &10_000_000);
+    token_b.mint(&user, &10_000_000);
+
+    assert!(liqpool.try_deposit(&user, &user, &2_000_000, &0, &2_000_000, &0).is_err());
+    liqpool.deposit(&user, &user, &1_000_000, &0, &1_000_000, &0);
+
+    // 1% hook fee instead of the pool's 0.25%
+    assert_eq!(liqpool.get_fee(), 25);
+    assert_eq!(liqpool.quote_swap_fee(&user, &true, &10_000), 100);
+    let out = liqpool.swap_exact_input(&user, &user, &true, &10_000, &0, &None, &None);
+    assert_eq!(out, crate::get_amount_out(10_000, 1_000_000, 1_000_000, 100));
+    assert_eq!(hooks.last_out(), out);
+}
+
+#[test]
+fn test_permissioned_pool_gates_deposits_swaps_and_shares() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let member = create_account(&e);
+    let outsider = create_account(&e);
+
+    // Asset contracts need a trustline to credit an account, the share token contract doesn't
+    let token_a = create_named_token(&e, &admin, "AAA");
+    let token_b = create_named_token(&e, &admin, "BBB");
+    let liqpool = create_liqpool_contract(&e, &install_token_wasm(&e), &token_a.address, &token_b.address, &None);
+
+    for user in [&member, &outsider] {
+        token_a.mint(user, &10_000_000);
+        token_b.mint(user, &10_000_000);
+    }
+
+    // Outsider deposits while the pool is still open
+    liqpool.deposit(&outsider, &outsider, &1_000_000, &0, &1_000_000, &0);
+
+    liqpool.set_permissioned(&true);
+    liqpool.set_allowed(&member, &true);
+    assert!(liqpool.is_permissioned());
+    assert!(liqpool.is_allowed(&member));
+    assert!(!liqpool.is_allowed(&outsider));
+
+    // Contracts are only let in once allowed, like a vault holding shares for its own members
+    let vault = Address::random(&e);
+    assert!(liqpool.try_swap_exact_input(&member, &vault, &true, &10_000, &0, &None, &None).is_err());
+    liqpool.set_allowed(&vault, &true);
+    liqpool.swap_exact_input(&member, &vault, &true, &10_000, &0, &None, &None);
+
+    liqpool.deposit(&member, &member, &1_000_000, &0, &1_000_000, &0);
+    assert!(liqpool.try_deposit(&outsider, &outsider, &1_000_000, &0, &1_000_000, &0).is_err());
+    assert!(liqpool.try_deposit(&member, &outsider, &1_000_000, &0, &1_000_000, &0).is_err());
+
+    liqpool.swap_exact_input(&member, &member, &true, &10_000, &0, &None, &None);
+    assert!(liqpool.try_swap_exact_input(&outsider, &outsider, &true, &10_000, &0, &None, &None).is_err());
+    assert!(liqpool.try_swap_exact_output(&member, &outsider, &true, &1_000, &10_000, &None, &None).is_err());
+
+    // Shares can only be transferred to allowed addresses
+    let share = lptoken::Client::new(&e, &liqpool.share_id());
+    assert!(share.try_transfer(&member, &outsider, &1_000).is_err());
+    share.transfer(&member, &vault, &1_000);
+    assert_eq!(share.balance(&vault), 1_000);
+
+    // Holders that are not allowed can still leave
+    let shares = liqpool.get_lptoken_balance(&outsider);
+    liqpool.withdraw(&outsider, &outsider, &shares, &0, &0);
+    assert_eq!(liqpool.get_lptoken_balance(&outsider), 0);
+}
+
 // #![cfg(test)]
 // extern crate std;
 
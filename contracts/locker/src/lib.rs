@@ -0,0 +1,239 @@
+#![no_std]
+#![allow(clippy::too_many_arguments)]
+
+mod test;
+
+use soroban_sdk::{
+    contract, contractimpl, contractmeta, contracttype, token, Address, Env, IntoVal, TryFromVal, Val, Vec,
+};
+
+
+mod liquiditypool{
+
+    soroban_sdk::contractimport!(
+        file = "../../target/wasm32-unknown-unknown/release/soroban_liquidity_pool_contract.wasm"
+    );
+}
+
+#[derive(Clone)]
+#[contracttype]
+pub enum DataKey {
+    LockCount,
+    Lock(u32),
+    OwnerLocks(Address),
+    PoolLocks(Address),
+}
+
+// LP shares of "pool" held for "owner". The shares vest linearly from "vesting_start" to "unlock_time",
+// so a lock with vesting_start == unlock_time releases everything at once at unlock_time.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Lock {
+    pub owner: Address,
+    pub pool: Address,
+    pub lp_token: Address,
+    pub amount: i128,
+    pub withdrawn: i128,
+    pub vesting_start: u64,
+    pub unlock_time: u64,
+}
+
+
+fn get_lock_count(e: &Env) -> u32 {
+    e.storage().instance().get(&DataKey::LockCount).unwrap_or(0)
+}
+
+// Persistent entries stay alive for 30 days after they were last read or written
+const DAY_IN_LEDGERS: u32 = 17280;
+const PERSISTENT_BUMP_AMOUNT: u32 = 30 * DAY_IN_LEDGERS;
+const PERSISTENT_LIFETIME_THRESHOLD: u32 = PERSISTENT_BUMP_AMOUNT - DAY_IN_LEDGERS;
+
+// Reads a persistent entry, extending its lifetime when it exists
+fn get_persistent<K: IntoVal<Env, Val>, V: TryFromVal<Env, Val>>(e: &Env, key: &K) -> Option<V> {
+    let value = e.storage().persistent().get(key);
+    if value.is_some() {
+        e.storage()
+            .persistent()
+            .bump(key, PERSISTENT_LIFETIME_THRESHOLD, PERSISTENT_BUMP_AMOUNT);
+    }
+    value
+}
+
+fn put_persistent<K: IntoVal<Env, Val>, V: IntoVal<Env, Val>>(e: &Env, key: &K, value: &V) {
+    e.storage().persistent().set(key, value);
+    e.storage()
+        .persistent()
+        .bump(key, PERSISTENT_LIFETIME_THRESHOLD, PERSISTENT_BUMP_AMOUNT);
+}
+
+fn get_lock(e: &Env, lock_id: u32) -> Lock {
+    get_persistent(e, &DataKey::Lock(lock_id))
+        .unwrap_or_else(|| panic!("Lock not found"))
+}
+
+fn get_owner_locks(e: &Env, owner: Address) -> Vec<u32> {
+    get_persistent(e, &DataKey::OwnerLocks(owner)).unwrap_or(Vec::new(e))
+}
+
+fn get_pool_locks(e: &Env, pool: Address) -> Vec<u32> {
+    get_persistent(e, &DataKey::PoolLocks(pool)).unwrap_or(Vec::new(e))
+}
+
+fn put_lock_count(e: &Env, count: u32) {
+    e.storage().instance().set(&DataKey::LockCount, &count);
+}
+
+fn put_lock(e: &Env, lock_id: u32, lock: &Lock) {
+    put_persistent(e, &DataKey::Lock(lock_id), lock);
+}
+
+fn put_owner_locks(e: &Env, owner: Address, locks: &Vec<u32>) {
+    put_persistent(e, &DataKey::OwnerLocks(owner), locks);
+}
+
+fn put_pool_locks(e: &Env, pool: Address, locks: &Vec<u32>) {
+    put_persistent(e, &DataKey::PoolLocks(pool), locks);
+}
+
+// Shares of the lock vested at the current ledger time, withdrawn or not
+fn vested_amount(e: &Env, lock: &Lock) -> i128 {
+    let now = e.ledger().timestamp();
+
+    if now >= lock.unlock_time {
+        lock.amount
+    } else if now <= lock.vesting_start {
+        0
+    } else {
+        lock.amount * (now - lock.vesting_start) as i128 / (lock.unlock_time - lock.vesting_start) as i128
+    }
+}
+
+fn withdrawable_amount(e: &Env, lock: &Lock) -> i128 {
+    (vested_amount(e, lock) - lock.withdrawn).max(0)
+}
+
+
+// Metadata that is added on to the WASM custom section
+contractmeta!(
+    key = "Locker",
+    val = "Time-locked LP shares of SorobanSwap pools"
+);
+
+pub trait LockerTrait {
+    // Locks "amount" LP shares of "pool" from "owner". The shares vest linearly from "vesting_start"
+    // to "unlock_time"; pass vesting_start == unlock_time to release all of them at unlock_time.
+    // Returns the lock id.
+    fn lock(e: Env, owner: Address, pool: Address, amount: i128, vesting_start: u64, unlock_time: u64) -> u32;
+
+    // Moves the unlock time of "lock_id" later. Locks can never be shortened.
+    fn extend_lock(e: Env, lock_id: u32, unlock_time: u64);
+
+    // Sends "amount" of the vested shares of "lock_id" back to its owner
+    fn withdraw(e: Env, lock_id: u32, amount: i128);
+
+    fn get_lock(e: Env, lock_id: u32) -> Lock;
+
+    // Returns the shares of "lock_id" its owner can withdraw now
+    fn get_withdrawable(e: Env, lock_id: u32) -> i128;
+
+    // Returns the ids of the locks of "owner"
+    fn get_locks(e: Env, owner: Address) -> Vec<u32>;
+
+    // Returns the LP shares of "pool" held by the locker that have not vested yet
+    fn get_locked_liquidity(e: Env, pool: Address) -> i128;
+}
+
+#[contract]
+struct Locker;
+
+#[contractimpl]
+impl LockerTrait for Locker {
+    fn lock(e: Env, owner: Address, pool: Address, amount: i128, vesting_start: u64, unlock_time: u64) -> u32 {
+        owner.require_auth();
+
+        assert!(amount > 0, "Amount must be grater then 0");
+        assert!(vesting_start >= e.ledger().timestamp(), "vesting_start must not be in the past");
+        assert!(vesting_start <= unlock_time, "vesting_start must not be after unlock_time");
+        assert!(unlock_time > e.ledger().timestamp(), "unlock_time must be in the future");
+
+        let lp_token = liquiditypool::Client::new(&e, &pool).share_id();
+        token::Client::new(&e, &lp_token).transfer(&owner, &e.current_contract_address(), &amount);
+
+        let lock_id = get_lock_count(&e);
+        put_lock_count(&e, lock_id + 1);
+
+        put_lock(&e, lock_id, &Lock {
+            owner: owner.clone(),
+            pool: pool.clone(),
+            lp_token,
+            amount,
+            withdrawn: 0,
+            vesting_start,
+            unlock_time,
+        });
+
+        let mut locks = get_owner_locks(&e, owner.clone());
+        locks.push_back(lock_id);
+        put_owner_locks(&e, owner, &locks);
+
+        let mut pool_locks = get_pool_locks(&e, pool.clone());
+        pool_locks.push_back(lock_id);
+        put_pool_locks(&e, pool, &pool_locks);
+
+        lock_id
+    }
+
+    fn extend_lock(e: Env, lock_id: u32, unlock_time: u64) {
+        let mut lock = get_lock(&e, lock_id);
+        lock.owner.require_auth();
+
+        assert!(unlock_time > lock.unlock_time, "Lock can only be extended");
+
+        lock.unlock_time = unlock_time;
+        put_lock(&e, lock_id, &lock);
+    }
+
+    fn withdraw(e: Env, lock_id: u32, amount: i128) {
+        let mut lock = get_lock(&e, lock_id);
+        lock.owner.require_auth();
+
+        assert!(amount > 0, "Amount must be grater then 0");
+        assert!(amount <= withdrawable_amount(&e, &lock), "Amount exceeds unlocked shares");
+
+        lock.withdrawn += amount;
+        put_lock(&e, lock_id, &lock);
+
+        // Emptied locks no longer count towards the pool's locked liquidity
+        if lock.withdrawn == lock.amount {
+            let mut pool_locks = get_pool_locks(&e, lock.pool.clone());
+            if let Some(index) = pool_locks.first_index_of(lock_id) {
+                pool_locks.remove(index);
+            }
+            put_pool_locks(&e, lock.pool.clone(), &pool_locks);
+        }
+
+        token::Client::new(&e, &lock.lp_token).transfer(&e.current_contract_address(), &lock.owner, &amount);
+    }
+
+    fn get_lock(e: Env, lock_id: u32) -> Lock {
+        get_lock(&e, lock_id)
+    }
+
+    fn get_withdrawable(e: Env, lock_id: u32) -> i128 {
+        withdrawable_amount(&e, &get_lock(&e, lock_id))
+    }
+
+    fn get_locks(e: Env, owner: Address) -> Vec<u32> {
+        get_owner_locks(&e, owner)
+    }
+
+    fn get_locked_liquidity(e: Env, pool: Address) -> i128 {
+        get_pool_locks(&e, pool)
+            .iter()
+            .map(|lock_id| {
+                let lock = get_lock(&e, lock_id);
+                lock.amount - vested_amount(&e, &lock)
+            })
+            .sum()
+    }
+}
@@ -0,0 +1,182 @@
+#![cfg(test)]
+extern crate std;
+
+use crate::{liquiditypool, Locker, LockerClient};
+
+use soroban_sdk::{
+    testutils::{Address as _, Ledger, MockAuth, MockAuthInvoke},
+    token, vec, Address, Env, IntoVal, String,
+};
+
+mod token_contract {
+    soroban_sdk::contractimport!(
+        file = "../../target/wasm32-unknown-unknown/release/soroban_token_contract.wasm"
+    );
+}
+
+fn create_token_contract<'a>(e: &Env, admin: &Address) -> token_contract::Client<'a> {
+    token_contract::Client::new(e, &e.register_stellar_asset_contract(admin.clone()))
+}
+
+fn create_liqpool_contract<'a>(e: &Env, admin: &Address, token_a: &Address, token_b: &Address) -> liquiditypool::Client<'a> {
+    let liqpool = liquiditypool::Client::new(e, &e.register_contract_wasm(None, liquiditypool::WASM));
+    liqpool.initialize(
+        &e.deployer().upload_contract_wasm(token_contract::WASM),
+        token_a,
+        token_b,
+        &Some(String::from_slice(e, "Pool Share")),
+        &Some(String::from_slice(e, "POOL")),
+        admin,
+        &None,
+    );
+    liqpool
+}
+
+#[test]
+fn test_vesting_lock() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+    e.ledger().with_mut(|l| l.timestamp = 1_000);
+
+    let admin = Address::random(&e);
+    let owner = Address::random(&e);
+
+    let token_a = create_token_contract(&e, &admin);
+    let token_b = create_token_contract(&e, &admin);
+    let liqpool = create_liqpool_contract(&e, &admin, &token_a.address, &token_b.address);
+
+    token_a.mint(&owner, &10_000_000);
+    token_b.mint(&owner, &10_000_000);
+    liqpool.deposit(&owner, &owner, &1_000_000, &0, &1_000_000, &0);
+
+    let locker = LockerClient::new(&e, &e.register_contract(None, Locker {}));
+    let lp_token = token::Client::new(&e, &liqpool.share_id());
+    let shares = lp_token.balance(&owner);
+
+    let lock_id = locker.lock(&owner, &liqpool.address, &100_000, &2_000, &3_000);
+    assert_eq!(locker.get_locks(&owner), vec![&e, lock_id]);
+    assert_eq!(locker.get_locked_liquidity(&liqpool.address), 100_000);
+    assert_eq!(lp_token.balance(&owner), shares - 100_000);
+    assert_eq!(locker.get_withdrawable(&lock_id), 0);
+
+    // Half way through the vesting
+    e.ledger().with_mut(|l| l.timestamp = 2_500);
+    assert_eq!(locker.get_withdrawable(&lock_id), 50_000);
+
+    assert_eq!(locker.get_locked_liquidity(&liqpool.address), 50_000);
+
+    locker.withdraw(&lock_id, &30_000);
+    assert_eq!(locker.get_withdrawable(&lock_id), 20_000);
+    assert_eq!(locker.get_locked_liquidity(&liqpool.address), 50_000);
+    assert_eq!(lp_token.balance(&owner), shares - 70_000);
+
+    // Doubling the vesting period halves what has vested so far
+    locker.extend_lock(&lock_id, &4_000);
+    assert_eq!(locker.get_withdrawable(&lock_id), 0);
+    assert_eq!(locker.get_locked_liquidity(&liqpool.address), 75_000);
+
+    e.ledger().with_mut(|l| l.timestamp = 4_000);
+    locker.withdraw(&lock_id, &70_000);
+    assert_eq!(locker.get_locked_liquidity(&liqpool.address), 0);
+    assert_eq!(lp_token.balance(&owner), shares);
+}
+
+// Pool with "owner" holding LP shares, and a locker
+fn setup_locker<'a>(e: &Env, owner: &Address) -> (LockerClient<'a>, liquiditypool::Client<'a>) {
+    let admin = Address::random(e);
+
+    let token_a = create_token_contract(e, &admin);
+    let token_b = create_token_contract(e, &admin);
+    let liqpool = create_liqpool_contract(e, &admin, &token_a.address, &token_b.address);
+
+    token_a.mint(owner, &10_000_000);
+    token_b.mint(owner, &10_000_000);
+    liqpool.deposit(owner, owner, &1_000_000, &0, &1_000_000, &0);
+
+    let locker = LockerClient::new(e, &e.register_contract(None, Locker {}));
+    (locker, liqpool)
+}
+
+#[test]
+fn test_withdraw_before_vesting_fails() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+    e.ledger().with_mut(|l| l.timestamp = 1_000);
+
+    let owner = Address::random(&e);
+    let (locker, liqpool) = setup_locker(&e, &owner);
+    let lock_id = locker.lock(&owner, &liqpool.address, &100_000, &2_000, &3_000);
+
+    assert!(locker.try_withdraw(&lock_id, &1).is_err());
+
+    // Nothing has vested yet at vesting_start
+    e.ledger().with_mut(|l| l.timestamp = 2_000);
+    assert!(locker.try_withdraw(&lock_id, &1).is_err());
+
+    // Only the vested part can be withdrawn
+    e.ledger().with_mut(|l| l.timestamp = 2_500);
+    assert!(locker.try_withdraw(&lock_id, &50_001).is_err());
+    assert_eq!(locker.get_locked_liquidity(&liqpool.address), 50_000);
+}
+
+#[test]
+fn test_only_owner_can_withdraw() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+    e.ledger().with_mut(|l| l.timestamp = 1_000);
+
+    let owner = Address::random(&e);
+    let attacker = Address::random(&e);
+    let (locker, liqpool) = setup_locker(&e, &owner);
+    let lock_id = locker.lock(&owner, &liqpool.address, &100_000, &1_000, &1_500);
+
+    e.ledger().with_mut(|l| l.timestamp = 2_000);
+    e.mock_auths(&[MockAuth {
+        address: &attacker,
+        invoke: &MockAuthInvoke {
+            contract: &locker.address,
+            fn_name: "withdraw",
+            args: (lock_id, 100_000_i128).into_val(&e),
+            sub_invokes: &[],
+        },
+    }]);
+    assert!(locker.try_withdraw(&lock_id, &100_000).is_err());
+    assert_eq!(locker.get_lock(&lock_id).withdrawn, 0);
+}
+
+#[test]
+fn test_lock_cannot_be_shortened() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+    e.ledger().with_mut(|l| l.timestamp = 1_000);
+
+    let owner = Address::random(&e);
+    let (locker, liqpool) = setup_locker(&e, &owner);
+    let lock_id = locker.lock(&owner, &liqpool.address, &100_000, &2_000, &3_000);
+
+    assert!(locker.try_extend_lock(&lock_id, &2_500).is_err());
+    assert!(locker.try_extend_lock(&lock_id, &3_000).is_err());
+    assert_eq!(locker.get_lock(&lock_id).unlock_time, 3_000);
+}
+
+#[test]
+fn test_vesting_cannot_start_in_the_past() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+    e.ledger().with_mut(|l| l.timestamp = 1_000);
+
+    let owner = Address::random(&e);
+    let (locker, liqpool) = setup_locker(&e, &owner);
+    let lp_token = token::Client::new(&e, &liqpool.share_id());
+    let shares = lp_token.balance(&owner);
+
+    // Backdating the start would vest most of the shares right away
+    assert!(locker.try_lock(&owner, &liqpool.address, &100_000, &0, &3_000).is_err());
+    assert_eq!(lp_token.balance(&owner), shares);
+    assert_eq!(locker.get_locked_liquidity(&liqpool.address), 0);
+}
@@ -0,0 +1,404 @@
+#![no_std]
+
+mod test;
+mod lptoken;
+mod math;
+mod guard;
+
+
+use soroban_sdk::{
+    contract, contractimpl, contractmeta, token, Address, BytesN, ConversionError, Env, String,
+    TryFromVal, Val, Vec,
+};
+use lptoken::create_contract;
+use math::{nth_root, pow, SCALE};
+
+const MIN_TOKENS: u32 = 2;
+const MAX_TOKENS: u32 = 8;
+
+// Shares minted by the first deposit, whatever the amounts deposited
+const INITIAL_SHARES: i128 = 10_000_000_000;
+
+// Part of the first deposit's shares locked in the pool, so the supply can't be drained to a few shares
+const MINIMUM_LIQUIDITY: i128 = 1000;
+
+const SWAP_FEE_BPS: i128 = 25;
+
+// A single asset join may add at most this fraction of the token's reserve, in basis points
+const MAX_SINGLE_IN_BPS: i128 = 5000;
+
+// A single asset exit may take at most this fraction of the token's reserve, in basis points
+const MAX_SINGLE_OUT_BPS: i128 = 3000;
+
+#[derive(Clone, Copy)]
+#[repr(u32)]
+pub enum DataKey {
+    Tokens = 0,
+    Reserves = 1,
+    TokenShare = 2,
+    TotalShares = 3,
+    IsPoolInitialize = 4,
+    Locked = 5,
+}
+
+impl TryFromVal<Env, DataKey> for Val {
+    type Error = ConversionError;
+
+    fn try_from_val(_env: &Env, v: &DataKey) -> Result<Self, Self::Error> {
+        Ok((*v as u32).into())
+    }
+}
+
+fn check_pool_initialize(e: &Env) {
+    if e.storage().instance().has(&DataKey::IsPoolInitialize) {
+        panic!("Pool already initialized");
+    }
+    e.storage().instance().set(&DataKey::IsPoolInitialize, &true);
+}
+
+// The reentrancy guard, taken by every mutating entrypoint until reserves are updated
+fn lock(e: &Env) {
+    guard::lock(e, &DataKey::Locked);
+}
+
+fn unlock(e: &Env) {
+    guard::unlock(e, &DataKey::Locked);
+}
+
+fn get_tokens(e: &Env) -> Vec<Address> {
+    e.storage().instance().get(&DataKey::Tokens).unwrap()
+}
+
+fn get_reserves(e: &Env) -> Vec<i128> {
+    e.storage().instance().get(&DataKey::Reserves).unwrap()
+}
+
+fn get_token_share(e: &Env) -> Address {
+    e.storage().instance().get(&DataKey::TokenShare).unwrap()
+}
+
+fn get_total_shares(e: &Env) -> i128 {
+    e.storage().instance().get(&DataKey::TotalShares).unwrap()
+}
+
+fn put_reserves(e: &Env, reserves: &Vec<i128>) {
+    e.storage().instance().set(&DataKey::Reserves, reserves)
+}
+
+fn put_total_shares(e: &Env, amount: i128) {
+    e.storage().instance().set(&DataKey::TotalShares, &amount)
+}
+
+fn mint_shares(e: &Env, to: Address, amount: i128) {
+    lptoken::Client::new(e, &get_token_share(e)).mint(&to, &amount);
+    put_total_shares(e, get_total_shares(e) + amount);
+}
+
+fn burn_shares(e: &Env, from: Address, amount: i128) {
+    lptoken::Client::new(e, &get_token_share(e)).burn(&from, &amount);
+    put_total_shares(e, get_total_shares(e) - amount);
+}
+
+// Index of "token" in the pool's token list
+fn token_index(e: &Env, token: &Address) -> u32 {
+    match get_tokens(e).first_index_of(token) {
+        Some(i) => i,
+        None => panic!("token not in pool"),
+    }
+}
+
+// Part of a single asset join or exit left after the swap fee. Only the share of the amount that is
+// effectively swapped into the other tokens, (n - 1) / n of it, pays the fee.
+fn after_single_asset_fee(amount: i128, n: u32) -> i128 {
+    let n = n as i128;
+    amount - amount * (n - 1) / n * SWAP_FEE_BPS / 10000
+}
+
+// With equal weights the invariant is the product of all reserves, so a swap between two tokens
+// prices like a constant product pool of those two reserves
+pub fn get_amount_out(amount_in: i128, reserve_in: i128, reserve_out: i128) -> i128 {
+    assert!(amount_in > 0, "Amount must be grater then 0");
+    assert!(reserve_in > 0 && reserve_out > 0, "ERROR_INSUFFICIENT_LIQUIDITY");
+
+    let amount_in_with_fee = amount_in * (10000 - SWAP_FEE_BPS);
+    let numerator = amount_in_with_fee * reserve_out;
+    let denominator = reserve_in * 10000 + amount_in_with_fee;
+
+    numerator / denominator
+}
+
+// Shares minted for adding "amount_in" of one token with "reserve": total * ((1 + in / reserve)^(1/n) - 1)
+fn get_single_join_shares(amount_in: i128, reserve: i128, total_shares: i128, n: u32) -> i128 {
+    assert!(amount_in > 0, "Amount must be grater then 0");
+    assert!(amount_in * 10000 <= reserve * MAX_SINGLE_IN_BPS, "single asset join too large");
+
+    let amount_in = after_single_asset_fee(amount_in, n);
+    let ratio = (reserve + amount_in) * SCALE / reserve;
+
+    total_shares * (nth_root(ratio, n) - SCALE) / SCALE
+}
+
+// Tokens paid out for redeeming "shares" into one token with "reserve": reserve * (1 - (1 - shares / total)^n)
+fn get_single_exit_amount(shares: i128, reserve: i128, total_shares: i128, n: u32) -> i128 {
+    assert!(shares > 0 && shares < total_shares, "invalid share amount");
+
+    let remaining = (total_shares - shares) * SCALE / total_shares;
+    let amount_out = reserve * (SCALE - pow(remaining, n)) / SCALE;
+    assert!(amount_out * 10000 <= reserve * MAX_SINGLE_OUT_BPS, "single asset exit too large");
+
+    after_single_asset_fee(amount_out, n)
+}
+
+
+// Metadata that is added on to the WASM custom section
+contractmeta!(
+    key = "Description",
+    val = "Equal weight pool of two to eight tokens with one LP token"
+);
+
+pub trait MultiAssetPoolTrait {
+    // Sets the tokens of the pool, all weighted equally, and deploys its share token
+    fn initialize(e: Env, token_wasm_hash: BytesN<32>, tokens: Vec<Address>, lptokenname: String, lptokensymbol: String);
+
+    // Returns the token contract address for the pool share token
+    fn share_id(e: Env) -> Address;
+
+    // Proportional join. Deposits at most "max_amounts" (one per token, in pool order) from "from" in the
+    // current reserve ratio and mints the shares to "to". The first deposit sets the ratio and takes
+    // max_amounts as is, MINIMUM_LIQUIDITY of its shares stay locked in the pool. Returns the shares minted.
+    fn deposit(e: Env, from: Address, to: Address, max_amounts: Vec<i128>, min_shares: i128) -> i128;
+
+    // Single asset join. Deposits "amount_in" of "token" only, at most half of its reserve, and mints the
+    // shares to "to". The part swapped into the other tokens pays the swap fee.
+    fn deposit_single(e: Env, from: Address, to: Address, token: Address, amount_in: i128, min_shares: i128) -> i128;
+
+    // Proportional exit. Burns "share_amount" shares of "from" and sends its part of every reserve to "to"
+    fn withdraw(e: Env, from: Address, to: Address, share_amount: i128, min_amounts: Vec<i128>) -> Vec<i128>;
+
+    // Single asset exit. Burns "share_amount" shares of "from" and sends "to" their value in "token" only,
+    // at most 30% of its reserve
+    fn withdraw_single(e: Env, from: Address, to: Address, share_amount: i128, token: Address, min_out: i128) -> i128;
+
+    // Sells "amount_in" of "token_in" for "token_out"
+    fn swap_exact_input(e: Env, from: Address, to: Address, token_in: Address, token_out: Address, amount_in: i128, min_out: i128) -> i128;
+
+    // Returns the output of selling "amount_in" of "token_in" for "token_out" at the current reserves
+    fn get_amount_out(e: Env, token_in: Address, token_out: Address, amount_in: i128) -> i128;
+
+    // Returns the pool's tokens, in the order used by the amount vectors
+    fn get_tokens(e: Env) -> Vec<Address>;
+
+    fn get_rsrvs(e: Env) -> Vec<i128>;
+
+    fn get_total_shares(e: Env) -> i128;
+}
+
+#[contract]
+struct MultiAssetPool;
+
+#[contractimpl]
+impl MultiAssetPoolTrait for MultiAssetPool {
+    fn initialize(e: Env, token_wasm_hash: BytesN<32>, tokens: Vec<Address>, lptokenname: String, lptokensymbol: String) {
+        check_pool_initialize(&e);
+
+        assert!(tokens.len() >= MIN_TOKENS && tokens.len() <= MAX_TOKENS, "pool must have 2 to 8 tokens");
+        for (i, token) in tokens.iter().enumerate() {
+            assert!(tokens.first_index_of(&token) == Some(i as u32), "duplicate token");
+        }
+
+        let share_contract = create_contract(&e, token_wasm_hash, &tokens);
+        lptoken::Client::new(&e, &share_contract).initialize(
+            &e.current_contract_address(),
+            &8u32,
+            &lptokenname,
+            &lptokensymbol,
+        );
+
+        let mut reserves = Vec::new(&e);
+        for _ in tokens.iter() {
+            reserves.push_back(0i128);
+        }
+
+        e.storage().instance().set(&DataKey::Tokens, &tokens);
+        e.storage().instance().set(&DataKey::TokenShare, &share_contract);
+        put_reserves(&e, &reserves);
+        put_total_shares(&e, 0);
+    }
+
+    fn share_id(e: Env) -> Address {
+        get_token_share(&e)
+    }
+
+    fn deposit(e: Env, from: Address, to: Address, max_amounts: Vec<i128>, min_shares: i128) -> i128 {
+        from.require_auth();
+        lock(&e);
+
+        let tokens = get_tokens(&e);
+        let mut reserves = get_reserves(&e);
+        let total_shares = get_total_shares(&e);
+        assert!(max_amounts.len() == tokens.len(), "one amount per token expected");
+
+        // Shares are limited by the scarcest token relative to its reserve
+        let shares = if total_shares == 0 {
+            for amount in max_amounts.iter() {
+                assert!(amount > 0, "Amount must be grater then 0");
+            }
+            mint_shares(&e, e.current_contract_address(), MINIMUM_LIQUIDITY);
+            INITIAL_SHARES - MINIMUM_LIQUIDITY
+        } else {
+            let mut shares = i128::MAX;
+            for (amount, reserve) in max_amounts.iter().zip(reserves.iter()) {
+                shares = shares.min(amount * total_shares / reserve);
+            }
+            shares
+        };
+
+        if shares <= 0 || shares < min_shares {
+            panic!("Not satisfied minimum shares")
+        }
+
+        for i in 0..tokens.len() {
+            let reserve = reserves.get(i).unwrap();
+            let amount = if total_shares == 0 {
+                max_amounts.get(i).unwrap()
+            } else {
+                // Rounded up so the deposit never dilutes existing shares
+                (reserve * shares + total_shares - 1) / total_shares
+            };
+
+            token::Client::new(&e, &tokens.get(i).unwrap()).transfer(&from, &e.current_contract_address(), &amount);
+            reserves.set(i, reserve + amount);
+        }
+
+        mint_shares(&e, to, shares);
+        put_reserves(&e, &reserves);
+        unlock(&e);
+
+        shares
+    }
+
+    fn deposit_single(e: Env, from: Address, to: Address, token: Address, amount_in: i128, min_shares: i128) -> i128 {
+        from.require_auth();
+        lock(&e);
+
+        let total_shares = get_total_shares(&e);
+        assert!(total_shares > 0, "ERROR_INSUFFICIENT_LIQUIDITY");
+
+        let i = token_index(&e, &token);
+        let mut reserves = get_reserves(&e);
+        let reserve = reserves.get(i).unwrap();
+
+        let shares = get_single_join_shares(amount_in, reserve, total_shares, reserves.len());
+        if shares <= 0 || shares < min_shares {
+            panic!("Not satisfied minimum shares")
+        }
+
+        token::Client::new(&e, &token).transfer(&from, &e.current_contract_address(), &amount_in);
+        reserves.set(i, reserve + amount_in);
+
+        mint_shares(&e, to, shares);
+        put_reserves(&e, &reserves);
+        unlock(&e);
+
+        shares
+    }
+
+    fn withdraw(e: Env, from: Address, to: Address, share_amount: i128, min_amounts: Vec<i128>) -> Vec<i128> {
+        from.require_auth();
+        lock(&e);
+
+        let tokens = get_tokens(&e);
+        let mut reserves = get_reserves(&e);
+        let total_shares = get_total_shares(&e);
+        assert!(min_amounts.len() == tokens.len(), "one amount per token expected");
+        assert!(share_amount > 0 && share_amount <= total_shares, "invalid share amount");
+
+        burn_shares(&e, from, share_amount);
+
+        let mut amounts = Vec::new(&e);
+        for i in 0..tokens.len() {
+            let reserve = reserves.get(i).unwrap();
+            let amount = reserve * share_amount / total_shares;
+            if amount < min_amounts.get(i).unwrap() {
+                panic!("min not satisfied");
+            }
+
+            token::Client::new(&e, &tokens.get(i).unwrap()).transfer(&e.current_contract_address(), &to, &amount);
+            reserves.set(i, reserve - amount);
+            amounts.push_back(amount);
+        }
+
+        put_reserves(&e, &reserves);
+        unlock(&e);
+
+        amounts
+    }
+
+    fn withdraw_single(e: Env, from: Address, to: Address, share_amount: i128, token: Address, min_out: i128) -> i128 {
+        from.require_auth();
+        lock(&e);
+
+        let i = token_index(&e, &token);
+        let mut reserves = get_reserves(&e);
+        let reserve = reserves.get(i).unwrap();
+
+        let amount_out = get_single_exit_amount(share_amount, reserve, get_total_shares(&e), reserves.len());
+        if amount_out < min_out {
+            panic!("Not satisfied minimum out")
+        }
+
+        burn_shares(&e, from, share_amount);
+        token::Client::new(&e, &token).transfer(&e.current_contract_address(), &to, &amount_out);
+        reserves.set(i, reserve - amount_out);
+
+        put_reserves(&e, &reserves);
+        unlock(&e);
+
+        amount_out
+    }
+
+    fn swap_exact_input(e: Env, from: Address, to: Address, token_in: Address, token_out: Address, amount_in: i128, min_out: i128) -> i128 {
+        from.require_auth();
+        lock(&e);
+
+        let (i, j) = (token_index(&e, &token_in), token_index(&e, &token_out));
+        assert!(i != j, "identical tokens");
+
+        let mut reserves = get_reserves(&e);
+        let (reserve_in, reserve_out) = (reserves.get(i).unwrap(), reserves.get(j).unwrap());
+
+        let amount_out = get_amount_out(amount_in, reserve_in, reserve_out);
+        if amount_out < min_out {
+            panic!("Not satisfied minimum out")
+        }
+
+        token::Client::new(&e, &token_in).transfer(&from, &e.current_contract_address(), &amount_in);
+        token::Client::new(&e, &token_out).transfer(&e.current_contract_address(), &to, &amount_out);
+
+        reserves.set(i, reserve_in + amount_in);
+        reserves.set(j, reserve_out - amount_out);
+        put_reserves(&e, &reserves);
+        unlock(&e);
+
+        amount_out
+    }
+
+    fn get_amount_out(e: Env, token_in: Address, token_out: Address, amount_in: i128) -> i128 {
+        let reserves = get_reserves(&e);
+        let (i, j) = (token_index(&e, &token_in), token_index(&e, &token_out));
+
+        get_amount_out(amount_in, reserves.get(i).unwrap(), reserves.get(j).unwrap())
+    }
+
+    fn get_tokens(e: Env) -> Vec<Address> {
+        get_tokens(&e)
+    }
+
+    fn get_rsrvs(e: Env) -> Vec<i128> {
+        get_reserves(&e)
+    }
+
+    fn get_total_shares(e: Env) -> i128 {
+        get_total_shares(&e)
+    }
+}
@@ -0,0 +1,21 @@
+#![allow(unused)]
+use soroban_sdk::{xdr::ToXdr, Address, Bytes, BytesN, Env, Vec};
+
+soroban_sdk::contractimport!(
+    file = "../../target/wasm32-unknown-unknown/release/soroban_token_contract.wasm"
+);
+
+pub fn create_contract(
+    e: &Env,
+    token_wasm_hash: BytesN<32>,
+    tokens: &Vec<Address>,
+) -> Address {
+    let mut salt = Bytes::new(e);
+    for token in tokens.iter() {
+        salt.append(&token.to_xdr(e));
+    }
+    let salt = e.crypto().sha256(&salt);
+    e.deployer()
+        .with_current_contract(salt)
+        .deploy(token_wasm_hash)
+}
@@ -0,0 +1,28 @@
+// Fixed point math with 18 decimals for single asset joins and exits
+
+pub const SCALE: i128 = 1_000_000_000_000_000_000;
+
+// ("x" / SCALE)^"n", scaled by SCALE
+pub fn pow(x: i128, n: u32) -> i128 {
+    let mut result = SCALE;
+    for _ in 0..n {
+        result = result * x / SCALE;
+    }
+    result
+}
+
+// ("x" / SCALE)^(1 / "n"), scaled by SCALE, for x >= SCALE. Newton's method started above the root,
+// so the estimate decreases until it stops moving.
+pub fn nth_root(x: i128, n: u32) -> i128 {
+    assert!(x >= SCALE, "root of value below 1");
+
+    let n_i = n as i128;
+    let mut root = x;
+    loop {
+        let next = ((n_i - 1) * root + x * SCALE / pow(root, n - 1)) / n_i;
+        if next >= root {
+            return root;
+        }
+        root = next;
+    }
+}
@@ -0,0 +1,108 @@
+#![cfg(test)]
+extern crate std;
+
+use crate::{get_amount_out, lptoken, math, MultiAssetPool, MultiAssetPoolClient, INITIAL_SHARES, MINIMUM_LIQUIDITY};
+
+use soroban_sdk::{testutils::Address as _, vec, Address, Env, String};
+
+fn create_token_contract<'a>(e: &Env, admin: &Address) -> lptoken::Client<'a> {
+    lptoken::Client::new(e, &e.register_stellar_asset_contract(admin.clone()))
+}
+
+#[test]
+fn test_fixed_point_math() {
+    assert_eq!(math::pow(2 * math::SCALE, 3), 8 * math::SCALE);
+    assert_eq!(math::nth_root(8 * math::SCALE, 3), 2 * math::SCALE);
+    assert_eq!(math::nth_root(math::SCALE, 5), math::SCALE);
+}
+
+#[test]
+fn test_index_pool() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let user1 = Address::random(&e);
+    let user2 = Address::random(&e);
+
+    let usdc = create_token_contract(&e, &admin);
+    let eth = create_token_contract(&e, &admin);
+    let btc = create_token_contract(&e, &admin);
+    for user in [&user1, &user2] {
+        usdc.mint(user, &10_000_000);
+        eth.mint(user, &10_000_000);
+        btc.mint(user, &10_000_000);
+    }
+
+    let pool = MultiAssetPoolClient::new(&e, &e.register_contract(None, MultiAssetPool {}));
+    pool.initialize(
+        &e.deployer().upload_contract_wasm(lptoken::WASM),
+        &vec![&e, usdc.address.clone(), eth.address.clone(), btc.address.clone()],
+        &String::from_slice(&e, "SorobanSwap Index"),
+        &String::from_slice(&e, "SSIDX"),
+    );
+    let share_token = lptoken::Client::new(&e, &pool.share_id());
+
+    let shares1 = pool.deposit(&user1, &user1, &vec![&e, 1_000_000, 2_000_000, 4_000_000], &0);
+    assert_eq!(shares1, INITIAL_SHARES - MINIMUM_LIQUIDITY);
+    assert_eq!(share_token.balance(&user1), shares1);
+    assert_eq!(share_token.balance(&pool.address), MINIMUM_LIQUIDITY);
+    assert_eq!(pool.get_rsrvs(), vec![&e, 1_000_000, 2_000_000, 4_000_000]);
+
+    // Any pair can be traded
+    let out = pool.swap_exact_input(&user2, &user2, &usdc.address, &btc.address, &10_000, &0);
+    assert_eq!(out, get_amount_out(10_000, 1_000_000, 4_000_000));
+    assert_eq!(pool.get_rsrvs(), vec![&e, 1_010_000, 2_000_000, 4_000_000 - out]);
+
+    // Proportional join at half of the reserves
+    let shares2 = pool.deposit(&user2, &user2, &vec![&e, 505_000, 1_000_000, 10_000_000], &0);
+    assert_eq!(shares2, INITIAL_SHARES / 2);
+
+    // Single asset round trip loses only fees and rounding
+    let joined = pool.deposit_single(&user2, &user2, &eth.address, &100_000, &0);
+    let eth_before = eth.balance(&user2);
+    let eth_out = pool.withdraw_single(&user2, &user2, &joined, &eth.address, &0);
+    assert!(eth_out < 100_000 && eth_out > 99_000);
+    assert_eq!(eth.balance(&user2), eth_before + eth_out);
+
+    let amounts = pool.withdraw(&user1, &user1, &shares1, &vec![&e, 0, 0, 0]);
+    assert_eq!(share_token.balance(&user1), 0);
+    assert_eq!(usdc.balance(&user1), 10_000_000 - 1_000_000 + amounts.get(0).unwrap());
+    assert_eq!(pool.get_total_shares(), shares2 + MINIMUM_LIQUIDITY);
+}
+
+#[test]
+fn test_single_asset_exit_is_capped() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let user = Address::random(&e);
+
+    let usdc = create_token_contract(&e, &admin);
+    let eth = create_token_contract(&e, &admin);
+    usdc.mint(&user, &10_000_000);
+    eth.mint(&user, &10_000_000);
+
+    let pool = MultiAssetPoolClient::new(&e, &e.register_contract(None, MultiAssetPool {}));
+    pool.initialize(
+        &e.deployer().upload_contract_wasm(lptoken::WASM),
+        &vec![&e, usdc.address.clone(), eth.address.clone()],
+        &String::from_slice(&e, "SorobanSwap Index"),
+        &String::from_slice(&e, "SSIDX"),
+    );
+    let shares = pool.deposit(&user, &user, &vec![&e, 1_000_000, 1_000_000], &0);
+
+    // A fifth of the shares is worth 36% of the eth reserve in eth only
+    assert!(pool.try_withdraw_single(&user, &user, &(shares / 5), &eth.address, &0).is_err());
+
+    // A tenth is worth 19%
+    let eth_out = pool.withdraw_single(&user, &user, &(shares / 10), &eth.address, &0);
+    assert!(eth_out > 180_000 && eth_out < 190_000);
+
+    // The locked shares keep the supply from being drained
+    pool.withdraw(&user, &user, &(shares - shares / 10), &vec![&e, 0, 0]);
+    assert_eq!(pool.get_total_shares(), MINIMUM_LIQUIDITY);
+}
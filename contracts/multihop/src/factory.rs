@@ -0,0 +1,29 @@
+use soroban_sdk::{xdr::ToXdr, Address, Bytes, BytesN, Env};
+
+// Orders a pair the way pools are keyed and deployed by the router, so (a, b) and (b, a)
+// resolve to the same pool
+pub fn sort_tokens(token_a: Address, token_b: Address) -> (Address, Address) {
+    if token_a == token_b {
+        panic!("identical tokens");
+    }
+    if token_a < token_b {
+        (token_a, token_b)
+    } else {
+        (token_b, token_a)
+    }
+}
+
+pub fn create_pool(
+    e: &Env,
+    pool_wasm_hash: BytesN<32>,
+    token_a: &Address,
+    token_b: &Address,
+) -> Address {
+    let mut salt = Bytes::new(e);
+    salt.append(&token_a.to_xdr(e));
+    salt.append(&token_b.to_xdr(e));
+    let salt = e.crypto().sha256(&salt);
+    e.deployer()
+        .with_current_contract(salt)
+        .deploy(pool_wasm_hash)
+}
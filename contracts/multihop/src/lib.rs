@@ -1,11 +1,18 @@
 #![no_std]
+#![allow(clippy::too_many_arguments)]
 
 mod test;
+mod factory;
+mod native;
 
 use soroban_sdk::{
-    contract, contractimpl, contractmeta, Address, Env, Vec,
+    auth::{ContractContext, InvokerContractAuthEntry, SubContractInvocation},
+    contract, contractimpl, contractmeta, contracttype, token, vec, Address, BytesN, ConversionError, Env,
+    IntoVal, Symbol, TryFromVal, Val, Vec,
 
 };
+use factory::{create_pool, sort_tokens};
+use native::native_token;
 
 
 mod liquiditypool{
@@ -15,6 +22,405 @@ mod liquiditypool{
     );
 }
 
+const FRACTION_DENOMINATOR: u32 = 10000;
+
+const MAX_ROUTE_HOPS: u32 = 4;
+
+#[derive(Clone, Copy)]
+#[repr(u32)]
+pub enum DataKey {
+    Admin = 0,
+    PoolWasmHash = 2,
+    TokenWasmHash = 3,
+}
+
+// Pool address of a pair, keyed by the sorted token addresses, and the tokens a token can be
+// routed to through registered pools. Both are per-pair persistent entries so anyone deploying
+// pairs can't grow what the router loads on every call.
+#[contracttype]
+#[derive(Clone)]
+pub enum PairKey {
+    Pool(Address, Address),
+    Routes(Address),
+}
+
+impl TryFromVal<Env, DataKey> for Val {
+    type Error = ConversionError;
+
+    fn try_from_val(_env: &Env, v: &DataKey) -> Result<Self, Self::Error> {
+        Ok((*v as u32).into())
+    }
+}
+
+// Tokens and reserves of a registered pool, read for every pool a route search visits
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PoolState {
+    pub pool: Address,
+    pub token_a: Address,
+    pub token_b: Address,
+    pub reserve_a: i128,
+    pub reserve_b: i128,
+}
+
+// Best path found by find_best_route, directly usable with the exact input swap entrypoints
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BestRoute {
+    pub pools: Vec<Address>,
+    pub directions: Vec<bool>,
+    pub amount_out: i128,
+}
+
+// One leg of a split swap. "fraction_bps" is the share of the total input routed through "pools",
+// in basis points of FRACTION_DENOMINATOR.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SwapRoute {
+    pub pools: Vec<Address>,
+    pub directions: Vec<bool>,
+    pub fraction_bps: u32,
+}
+
+
+fn get_admin(e: &Env) -> Address {
+    e.storage().instance().get(&DataKey::Admin).unwrap()
+}
+
+fn get_pool_wasm_hash(e: &Env) -> BytesN<32> {
+    e.storage().instance().get(&DataKey::PoolWasmHash).unwrap()
+}
+
+fn get_token_wasm_hash(e: &Env) -> BytesN<32> {
+    e.storage().instance().get(&DataKey::TokenWasmHash).unwrap()
+}
+
+// Persistent entries stay alive for 30 days after they were last read or written
+const DAY_IN_LEDGERS: u32 = 17280;
+const PERSISTENT_BUMP_AMOUNT: u32 = 30 * DAY_IN_LEDGERS;
+const PERSISTENT_LIFETIME_THRESHOLD: u32 = PERSISTENT_BUMP_AMOUNT - DAY_IN_LEDGERS;
+
+// Reads a persistent entry, extending its lifetime when it exists
+fn get_persistent<K: IntoVal<Env, Val>, V: TryFromVal<Env, Val>>(e: &Env, key: &K) -> Option<V> {
+    let value = e.storage().persistent().get(key);
+    if value.is_some() {
+        e.storage()
+            .persistent()
+            .bump(key, PERSISTENT_LIFETIME_THRESHOLD, PERSISTENT_BUMP_AMOUNT);
+    }
+    value
+}
+
+fn put_persistent<K: IntoVal<Env, Val>, V: IntoVal<Env, Val>>(e: &Env, key: &K, value: &V) {
+    e.storage().persistent().set(key, value);
+    e.storage()
+        .persistent()
+        .bump(key, PERSISTENT_LIFETIME_THRESHOLD, PERSISTENT_BUMP_AMOUNT);
+}
+
+fn get_pair_pool(e: &Env, token_a: Address, token_b: Address) -> Option<Address> {
+    let (token_a, token_b) = sort_tokens(token_a, token_b);
+    get_persistent(e, &PairKey::Pool(token_a, token_b))
+}
+
+fn put_pair_pool(e: &Env, token_a: Address, token_b: Address, pool: Address) {
+    let (token_a, token_b) = sort_tokens(token_a, token_b);
+    put_persistent(e, &PairKey::Pool(token_a, token_b), &pool);
+}
+
+fn get_paired_tokens(e: &Env, token: Address) -> Vec<Address> {
+    get_persistent(e, &PairKey::Routes(token)).unwrap_or(Vec::new(e))
+}
+
+fn add_paired_token(e: &Env, token: Address, paired: Address) {
+    let mut tokens = get_paired_tokens(e, token.clone());
+    if !tokens.contains(&paired) {
+        tokens.push_back(paired);
+        put_persistent(e, &PairKey::Routes(token), &tokens);
+    }
+}
+
+fn put_pool_wasm_hash(e: &Env, hash: BytesN<32>) {
+    e.storage().instance().set(&DataKey::PoolWasmHash, &hash);
+}
+
+fn put_token_wasm_hash(e: &Env, hash: BytesN<32>) {
+    e.storage().instance().set(&DataKey::TokenWasmHash, &hash);
+}
+
+// Records the pool for its pair and makes the pair routable. A pair has a single pool, which may
+// already have been deployed through add_liquidity.
+fn add_pool(e: &Env, pool: Address) {
+    let (token_a, token_b) = liquiditypool::Client::new(e, &pool).get_tokens();
+    if let Some(pair_pool) = get_pair_pool(e, token_a.clone(), token_b.clone()) {
+        if pair_pool != pool {
+            panic!("Pair already has a pool");
+        }
+    }
+
+    put_pair_pool(e, token_a.clone(), token_b.clone(), pool);
+    add_paired_token(e, token_a.clone(), token_b.clone());
+    add_paired_token(e, token_b, token_a);
+}
+
+// Returns the pool of the pair, deploying and initializing a new one through the router if the pair has none yet.
+// Deployed pools are not routable until the admin registers them.
+fn get_or_create_pair_pool(e: &Env, token_a: Address, token_b: Address) -> Address {
+    if let Some(pool) = get_pair_pool(e, token_a.clone(), token_b.clone()) {
+        return pool;
+    }
+
+    let (token_a, token_b) = sort_tokens(token_a, token_b);
+    let pool = create_pool(e, get_pool_wasm_hash(e), &token_a, &token_b);
+    liquiditypool::Client::new(e, &pool).initialize(
+        &get_token_wasm_hash(e),
+        &token_a,
+        &token_b,
+        &None,
+        &None,
+        &get_admin(e),
+        &None,
+    );
+
+    put_pair_pool(e, token_a, token_b, pool.clone());
+    pool
+}
+
+fn put_admin(e: &Env, admin: Address) {
+    e.storage().instance().set(&DataKey::Admin, &admin);
+}
+
+// Same constant product quote as the liquidity pool's get_amount_out, returning 0 instead of
+// panicking so empty pools are simply skipped while searching.
+fn get_amount_out(amount_in: i128, reserve_in: i128, reserve_out: i128, fee_bps: i128) -> i128 {
+    if amount_in <= 0 || reserve_in <= 0 || reserve_out <= 0 {
+        return 0;
+    }
+
+    let amount_in_with_fee = amount_in * (10000 - fee_bps);
+    let numerator = amount_in_with_fee * reserve_out;
+    let denominator = reserve_in * 10000 + amount_in_with_fee;
+
+    numerator / denominator
+}
+
+fn load_pool_state(env: &Env, pool: Address) -> PoolState {
+    let client = liquiditypool::Client::new(env, &pool);
+    let (token_a, token_b) = client.get_tokens();
+    let (reserve_a, reserve_b) = client.get_rsrvs();
+    PoolState { pool, token_a, token_b, reserve_a, reserve_b }
+}
+
+// Fee the pool would charge the router for selling "amount", including its hooks' adjustment.
+// None if the hooks would veto the swap.
+fn quote_swap_fee(env: &Env, pool: &Address, swap_x_to_y: bool, amount: i128) -> Option<i128> {
+    match liquiditypool::Client::new(env, pool).try_quote_swap_fee(&env.current_contract_address(), &swap_x_to_y, &amount) {
+        Ok(Ok(fee_bps)) => Some(fee_bps),
+        _ => None,
+    }
+}
+
+// Depth first search over the registered pairs of each token reached. A pool is used at most once
+// per path and the search stops extending a path as soon as it reaches "token_out".
+fn search_routes(env: &Env, token: &Address, token_out: &Address, amount: i128, hops_left: u32, path: &BestRoute, best: &mut BestRoute) {
+    if hops_left == 0 {
+        return;
+    }
+
+    for next_token in get_paired_tokens(env, token.clone()).iter() {
+        let pool = get_pair_pool(env, token.clone(), next_token.clone()).unwrap();
+        if path.pools.contains(&pool) {
+            continue;
+        }
+
+        let state = load_pool_state(env, pool);
+        let (swap_x_to_y, reserve_in, reserve_out) = if state.token_a == *token {
+            (true, state.reserve_a, state.reserve_b)
+        } else {
+            (false, state.reserve_b, state.reserve_a)
+        };
+
+        let fee_bps = match quote_swap_fee(env, &state.pool, swap_x_to_y, amount) {
+            Some(fee_bps) => fee_bps,
+            None => continue,
+        };
+        let amount_out = get_amount_out(amount, reserve_in, reserve_out, fee_bps);
+        if amount_out == 0 {
+            continue;
+        }
+
+        let mut next = path.clone();
+        next.pools.push_back(state.pool.clone());
+        next.directions.push_back(swap_x_to_y);
+        next.amount_out = amount_out;
+
+        if next_token == *token_out {
+            if amount_out > best.amount_out {
+                *best = next;
+            }
+        } else {
+            search_routes(env, &next_token, token_out, amount_out, hops_left - 1, &next, best);
+        }
+    }
+}
+
+// Returns the token sold and the token bought when swapping through the pool in the given direction
+fn get_swap_tokens(pool: &liquiditypool::Client, swap_x_to_y: bool) -> (Address, Address) {
+    let (token_a, token_b) = pool.get_tokens();
+    if swap_x_to_y {
+        (token_a, token_b)
+    } else {
+        (token_b, token_a)
+    }
+}
+
+// The router is the payer of every pool swap it makes, so the pool's transfer of the sold token
+// out of the router has to be pre-authorized before calling the pool.
+fn authorize_pool_transfer(env: &Env, pool: &liquiditypool::Client, sell_token: Address, amount_in: i128) {
+    env.authorize_as_current_contract(vec![
+        env,
+        InvokerContractAuthEntry::Contract(SubContractInvocation {
+            context: ContractContext {
+                contract: sell_token,
+                fn_name: Symbol::new(env, "transfer"),
+                args: (env.current_contract_address(), pool.address.clone(), amount_in).into_val(env),
+            },
+            sub_invocations: vec![env],
+        }),
+    ]);
+}
+
+// Swaps "amount_in" held by the router through the pool, the router receives the output
+fn swap_held_by_router(env: &Env, pool: &liquiditypool::Client, swap_x_to_y: bool, amount_in: i128, min_out: i128, max_price_impact_bps: Option<u32>, referrer: &Option<Address>) -> i128 {
+    let (sell_token, _) = get_swap_tokens(pool, swap_x_to_y);
+    authorize_pool_transfer(env, pool, sell_token, amount_in);
+
+    let router = env.current_contract_address();
+    pool.swap_exact_input(&router, &router, &swap_x_to_y, &amount_in, &min_out, &max_price_impact_bps, referrer)
+}
+
+// Fee on transfer variant of swap_held_by_router. Returns what the router actually received, which
+// can be less than the pool's output if the bought token is taxed on transfer.
+fn swap_held_by_router_supporting_fee_on_transfer(env: &Env, pool: &liquiditypool::Client, swap_x_to_y: bool, amount_in: i128, max_price_impact_bps: Option<u32>, referrer: &Option<Address>) -> i128 {
+    let router = env.current_contract_address();
+    let (sell_token, buy_token) = get_swap_tokens(pool, swap_x_to_y);
+
+    let buy_token_client = token::Client::new(env, &buy_token);
+    let balance_before = buy_token_client.balance(&router);
+
+    // The authorization only covers the router's next call, so nothing may be called in between
+    authorize_pool_transfer(env, pool, sell_token, amount_in);
+    pool.swap_exact_in_fee_on_transfer(&router, &router, &swap_x_to_y, &amount_in, &0, &max_price_impact_bps, referrer);
+
+    buy_token_client.balance(&router) - balance_before
+}
+
+// Returns the token paid into the first pool and the token received from the last pool of a route,
+// checking that every hop sells the token the previous hop bought
+fn get_route_tokens(env: &Env, pools: &Vec<Address>, directions: &Vec<bool>) -> (Address, Address) {
+    assert!(!pools.is_empty() && pools.len() == directions.len(), "pools and directions length mismatch");
+
+    let first_pool = liquiditypool::Client::new(env, &pools.get(0).unwrap());
+    let (sell_token, mut buy_token) = get_swap_tokens(&first_pool, directions.get(0).unwrap());
+
+    for (pool, swap_x_to_y) in pools.iter().zip(directions.iter()).skip(1) {
+        let (token_in, token_out) = get_swap_tokens(&liquiditypool::Client::new(env, &pool), swap_x_to_y);
+        assert!(token_in == buy_token, "route hops are not chained");
+        buy_token = token_out;
+    }
+
+    (sell_token, buy_token)
+}
+
+// Swaps "amount_in" held by the router through every pool of a route and returns the amount the router received
+fn swap_route_held_by_router(env: &Env, pools: &Vec<Address>, directions: &Vec<bool>, amount_in: i128, max_price_impact_bps: Option<u32>, referrer: &Option<Address>) -> i128 {
+    let mut amount = amount_in;
+
+    for (pool, swap_x_to_y) in pools.iter().zip(directions.iter()) {
+        let pool = liquiditypool::Client::new(env, &pool);
+        amount = swap_held_by_router(env, &pool, swap_x_to_y, amount, 0, max_price_impact_bps, referrer);
+    }
+
+    amount
+}
+
+// Runs an exact input swap along "pools". The input is pulled from "to" into the router, every
+// intermediate amount stays with the router and only the final output is paid to "recipient".
+fn swap_exact_input_path(env: &Env, to: &Address, recipient: &Address, pools: &Vec<Address>, directions: &Vec<bool>, x_in: i128, min_out: i128, max_price_impact_bps: Option<u32>, referrer: &Option<Address>) -> i128 {
+    let (sell_token, buy_token) = get_route_tokens(env, pools, directions);
+    token::Client::new(env, &sell_token).transfer(to, &env.current_contract_address(), &x_in);
+
+    let amount_out = swap_route_held_by_router(env, pools, directions, x_in, max_price_impact_bps, referrer);
+    if amount_out < min_out {
+        panic!("Not satisfied minimum out")
+    }
+
+    token::Client::new(env, &buy_token).transfer(&env.current_contract_address(), recipient, &amount_out);
+
+    amount_out
+}
+// Fee on transfer variant of swap_exact_input_path. Every transfer is measured by the receiver's balance
+// change, and "min_out" is checked against what "recipient" actually received.
+fn swap_exact_input_path_supporting_fee_on_transfer(env: &Env, to: &Address, recipient: &Address, pools: &Vec<Address>, directions: &Vec<bool>, x_in: i128, min_out: i128, max_price_impact_bps: Option<u32>, referrer: &Option<Address>) -> i128 {
+    let router = env.current_contract_address();
+    let (sell_token, buy_token) = get_route_tokens(env, pools, directions);
+
+    let sell_token_client = token::Client::new(env, &sell_token);
+    let balance_before = sell_token_client.balance(&router);
+    sell_token_client.transfer(to, &router, &x_in);
+    let mut amount = sell_token_client.balance(&router) - balance_before;
+
+    for (pool, swap_x_to_y) in pools.iter().zip(directions.iter()) {
+        let pool = liquiditypool::Client::new(env, &pool);
+        amount = swap_held_by_router_supporting_fee_on_transfer(env, &pool, swap_x_to_y, amount, max_price_impact_bps, referrer);
+    }
+
+    let buy_token_client = token::Client::new(env, &buy_token);
+    let balance_before = buy_token_client.balance(recipient);
+    buy_token_client.transfer(&router, recipient, &amount);
+    let amount_out = buy_token_client.balance(recipient) - balance_before;
+
+    if amount_out < min_out {
+        panic!("Not satisfied minimum out")
+    }
+
+    amount_out
+}
+// Single hop exact input swap through the pool registered for the pair
+fn swap_exact_input_pair(env: &Env, to: &Address, recipient: &Address, token_in: Address, token_out: Address, x_in: i128, min_out: i128, max_price_impact_bps: Option<u32>, referrer: &Option<Address>) -> i128 {
+    let pool = match get_pair_pool(env, token_in.clone(), token_out) {
+        Some(pool) => pool,
+        None => panic!("Pool does not exist"),
+    };
+    let (pool_token_a, _) = liquiditypool::Client::new(env, &pool).get_tokens();
+
+    swap_exact_input_path(env, to, recipient, &vec![env, pool], &vec![env, pool_token_a == token_in], x_in, min_out, max_price_impact_bps, referrer)
+}
+
+// Splits "x_in" across the routes by their fractions. The last route takes the rounding remainder so
+// the whole input is always spent.
+fn split_amount_in(routes: &Vec<SwapRoute>, x_in: i128) -> Vec<i128> {
+    let env = routes.env();
+    let mut amounts = Vec::new(env);
+    let mut total_bps = 0u32;
+    let mut allocated = 0i128;
+
+    for (i, route) in routes.iter().enumerate() {
+        assert!(route.fraction_bps > 0, "route fraction must be grater then 0");
+        total_bps += route.fraction_bps;
+
+        let amount = if i as u32 == routes.len() - 1 {
+            x_in - allocated
+        } else {
+            x_in * route.fraction_bps as i128 / FRACTION_DENOMINATOR as i128
+        };
+        allocated += amount;
+        amounts.push_back(amount);
+    }
+
+    assert!(total_bps == FRACTION_DENOMINATOR, "route fractions must sum to 10000");
+    amounts
+}
 
 // Metadata that is added on to the WASM custom section
 contractmeta!(
@@ -23,11 +429,62 @@ contractmeta!(
 );
 
 pub trait MultihopSwapTrait {
-   fn swap_exact_input_doublehop(env:Env,to:Address,pools:Vec<Address>,swap_x_to_y:bool,swap_y_to_z:bool,x_in:i128, z_min_out:i128)->i128;
-   fn swap_exact_input_triplehop(env:Env,to:Address,pools:Vec<Address>,swap_x_to_y:bool,swap_y_to_z:bool,swap_z_to_a:bool,x_in:i128, a_min_out:i128)->i128;
-   fn swap_exact_input_quadruplehop(env:Env,to:Address,pools:Vec<Address>,swap_x_to_y:bool,swap_y_to_z:bool,swap_z_to_a:bool,swap_a_to_b:bool,x_in:i128, b_min_out:i128)->i128;
+   // Sets the admin allowed to register pools for route finding, and the wasm hashes used to deploy
+   // new pools and their share tokens from add_liquidity
+   fn initialize(env:Env,admin:Address,pool_wasm_hash:BytesN<32>,token_wasm_hash:BytesN<32>);
+
+   // Makes the pool's pair routable by find_best_route. The pool becomes the pool of its pair, which
+   // must not already have a different one.
+   fn register_pool(env:Env,pool:Address);
+
+   // Returns the tokens "token" can be swapped to through a single registered pool
+   fn get_paired_tokens(env:Env,token:Address)->Vec<Address>;
+
+   // Returns the pool used for the pair, in either token order
+   fn get_pool(env:Env,token_a:Address,token_b:Address)->Option<Address>;
+
+   // Deposits into the pool of the pair, deploying it first if the pair has none. Amounts are given in the
+   // caller's token order and returned as (shares, amount_a, amount_b) in the same order. Pools deployed
+   // here can be traded directly but are only routed through once registered.
+   fn add_liquidity(env:Env,to:Address,token_a:Address,token_b:Address,desired_a:i128,min_a:i128,desired_b:i128,min_b:i128)->(i128,i128,i128);
+
+   // Redeems "liquidity" pool shares of the pair and returns (amount_a, amount_b) in the caller's token order
+   fn remove_liquidity(env:Env,to:Address,token_a:Address,token_b:Address,liquidity:i128,min_a:i128,min_b:i128)->(i128,i128);
+
+   // Explores paths of up to max_hops registered pools from token_in to token_out, following only the pairs
+   // of the tokens reached, and returns the path with the highest expected output at the current reserves.
+   // amount_out is 0 if no path exists. Each hop is quoted at the fee the pool would charge the router,
+   // hooks included, and pools whose hooks would veto the swap are skipped.
+   // Routes of 2 to 4 pools map onto the hop entrypoints, a 1 pool route is swapped on the pool directly
+   // and any length can be passed to swap_exact_input_split as a single route.
+   fn find_best_route(env:Env,token_in:Address,token_out:Address,amount_in:i128,max_hops:u32)->BestRoute;
+
+   // "to" pays x_in, the router holds every intermediate amount and the final output is paid to "recipient".
+   // An optional "referrer" is paid the referral share of the fee of every pool swapped through, and
+   // "max_price_impact_bps" caps the price impact of each of those swaps. Contract functions take at most
+   // 10 parameters, so the quadruple hop can't take the cap. Pass a 4 pool route to swap_exact_input_split for it.
+   fn swap_exact_input_doublehop(env:Env,to:Address,recipient:Address,pools:Vec<Address>,swap_x_to_y:bool,swap_y_to_z:bool,x_in:i128, z_min_out:i128,max_price_impact_bps:Option<u32>,referrer:Option<Address>)->i128;
+   fn swap_exact_input_triplehop(env:Env,to:Address,recipient:Address,pools:Vec<Address>,swap_x_to_y:bool,swap_y_to_z:bool,swap_z_to_a:bool,x_in:i128, a_min_out:i128,max_price_impact_bps:Option<u32>,referrer:Option<Address>)->i128;
+   fn swap_exact_input_quadruplehop(env:Env,to:Address,recipient:Address,pools:Vec<Address>,swap_x_to_y:bool,swap_y_to_z:bool,swap_z_to_a:bool,swap_a_to_b:bool,x_in:i128, b_min_out:i128,referrer:Option<Address>)->i128;
+
+   // Exact input swap along "pools" for tokens that take a fee on transfer. Amounts are taken from balance
+   // changes instead of nominal values and "min_out" applies to what "recipient" actually received.
+   // Named after the pool entrypoint it calls, which is shortened to fit the 32 character limit.
+   fn swap_exact_in_fee_on_transfer(env:Env,to:Address,recipient:Address,pools:Vec<Address>,directions:Vec<bool>,x_in:i128,min_out:i128,max_price_impact_bps:Option<u32>,referrer:Option<Address>)->i128;
+
+   // Splits x_in across parallel routes that share the same input and output token and executes them
+   // atomically. "min_out" is checked against the summed output of all routes.
+   fn swap_exact_input_split(env:Env,to:Address,recipient:Address,routes:Vec<SwapRoute>,x_in:i128,min_out:i128,max_price_impact_bps:Option<u32>,referrer:Option<Address>)->i128;
+
+   // Returns the Stellar Asset Contract address of native XLM, usable as a pool token like any other
+   fn get_native_token(env:Env)->Address;
+
+   // Sells x_in native XLM for token_out through the pool registered for the pair
+   fn swap_exact_native_for_tokens(env:Env,to:Address,recipient:Address,token_out:Address,x_in:i128,min_out:i128,max_price_impact_bps:Option<u32>,referrer:Option<Address>)->i128;
+
+   // Sells x_in of token_in for native XLM through the pool registered for the pair
+   fn swap_exact_tokens_for_native(env:Env,to:Address,recipient:Address,token_in:Address,x_in:i128,min_out:i128,max_price_impact_bps:Option<u32>,referrer:Option<Address>)->i128;
 
-   
 }
 
 #[contract]
@@ -35,98 +492,163 @@ struct MultihopSwap;
 
 #[contractimpl]
 impl MultihopSwapTrait for MultihopSwap {
-    
-    fn swap_exact_input_doublehop(env:Env,to:Address,pools:Vec<Address>,swap_x_to_y:bool,swap_y_to_z:bool,x_in:i128, z_min_out:i128)->i128{
+
+    fn initialize(env:Env,admin:Address,pool_wasm_hash:BytesN<32>,token_wasm_hash:BytesN<32>){
+        if env.storage().instance().has(&DataKey::Admin) {
+            panic!("Router already initialized");
+        }
+        put_admin(&env, admin);
+        put_pool_wasm_hash(&env, pool_wasm_hash);
+        put_token_wasm_hash(&env, token_wasm_hash);
+    }
+
+    fn register_pool(env:Env,pool:Address){
+        get_admin(&env).require_auth();
+
+        add_pool(&env, pool);
+    }
+
+    fn get_paired_tokens(env:Env,token:Address)->Vec<Address>{
+        get_paired_tokens(&env, token)
+    }
+
+    fn get_pool(env:Env,token_a:Address,token_b:Address)->Option<Address>{
+        get_pair_pool(&env, token_a, token_b)
+    }
+
+    fn add_liquidity(env:Env,to:Address,token_a:Address,token_b:Address,desired_a:i128,min_a:i128,desired_b:i128,min_b:i128)->(i128,i128,i128){
 
         to.require_auth();
-        
-        let first_pool = liquiditypool::Client::new(&env, &pools.get(0).unwrap());
-        let second_pool = liquiditypool::Client::new(&env, &pools.get(1).unwrap());
-        
-        
-        let y_out = if swap_x_to_y {
-            first_pool.swap_exact_input(&to, &true, &x_in, &0)
-        }else{
-            first_pool.swap_exact_input(&to, &false, &x_in, &0)
+
+        let pool = liquiditypool::Client::new(&env, &get_or_create_pair_pool(&env, token_a.clone(), token_b.clone()));
+        let (pool_token_a, _) = pool.get_tokens();
+        let same_order = pool_token_a == token_a;
+
+        let (shares, amount_a, amount_b) = if same_order {
+            let (shares, amount_a, amount_b, _, _) = pool.deposit(&to, &to, &desired_a, &min_a, &desired_b, &min_b);
+            (shares, amount_a, amount_b)
+        } else {
+            let (shares, amount_b, amount_a, _, _) = pool.deposit(&to, &to, &desired_b, &min_b, &desired_a, &min_a);
+            (shares, amount_a, amount_b)
         };
 
-        let z_out = if swap_y_to_z { 
-            second_pool.swap_exact_input(&to, &true, &y_out, &z_min_out)
-        }else{
-            second_pool.swap_exact_input(&to, &false, &y_out, &z_min_out)
+        if amount_a < min_a || amount_b < min_b {
+            panic!("min not satisfied");
+        }
+
+        (shares, amount_a, amount_b)
+    }
+
+    fn remove_liquidity(env:Env,to:Address,token_a:Address,token_b:Address,liquidity:i128,min_a:i128,min_b:i128)->(i128,i128){
+
+        to.require_auth();
+
+        let pool = match get_pair_pool(&env, token_a.clone(), token_b) {
+            Some(pool) => liquiditypool::Client::new(&env, &pool),
+            None => panic!("Pool does not exist"),
         };
+        let (pool_token_a, _) = pool.get_tokens();
+
+        if pool_token_a == token_a {
+            pool.withdraw(&to, &to, &liquidity, &min_a, &min_b)
+        } else {
+            let (amount_b, amount_a) = pool.withdraw(&to, &to, &liquidity, &min_b, &min_a);
+            (amount_a, amount_b)
+        }
+    }
+
+    fn find_best_route(env:Env,token_in:Address,token_out:Address,amount_in:i128,max_hops:u32)->BestRoute{
+        assert!(amount_in > 0, "Amount must be grater then 0");
+        assert!(max_hops > 0 && max_hops <= MAX_ROUTE_HOPS, "max_hops must be between 1 and 4");
+        assert!(token_in != token_out, "token_in and token_out must differ");
+
+        let path = BestRoute { pools: Vec::new(&env), directions: Vec::new(&env), amount_out: 0 };
+        let mut best = path.clone();
 
-        
-        z_out
+        search_routes(&env, &token_in, &token_out, amount_in, max_hops, &path, &mut best);
 
+        best
     }
 
-    fn swap_exact_input_triplehop(env:Env,to:Address,pools:Vec<Address>,swap_x_to_y:bool,swap_y_to_z:bool,swap_z_to_a:bool,x_in:i128, a_min_out:i128)->i128{
-        
+    fn swap_exact_input_doublehop(env:Env,to:Address,recipient:Address,pools:Vec<Address>,swap_x_to_y:bool,swap_y_to_z:bool,x_in:i128, z_min_out:i128,max_price_impact_bps:Option<u32>,referrer:Option<Address>)->i128{
+
         to.require_auth();
 
-        let first_pool = liquiditypool::Client::new(&env, &pools.get(0).unwrap());
-        let second_pool = liquiditypool::Client::new(&env, &pools.get(1).unwrap());
-        let third_pool = liquiditypool::Client::new(&env, &pools.get(2).unwrap());
+        swap_exact_input_path(&env, &to, &recipient, &pools, &vec![&env, swap_x_to_y, swap_y_to_z], x_in, z_min_out, max_price_impact_bps, &referrer)
 
-        let y_out = if swap_x_to_y {
-            first_pool.swap_exact_input(&to, &true, &x_in, &0)
-        }else{
-            first_pool.swap_exact_input(&to, &false, &x_in, &0)
-        };
+    }
 
-        let z_out = if swap_y_to_z {
-            second_pool.swap_exact_input(&to, &true, &y_out, &0)
-        }else{
-            second_pool.swap_exact_input(&to, &false, &y_out, &0)
-        };
+    fn swap_exact_input_triplehop(env:Env,to:Address,recipient:Address,pools:Vec<Address>,swap_x_to_y:bool,swap_y_to_z:bool,swap_z_to_a:bool,x_in:i128, a_min_out:i128,max_price_impact_bps:Option<u32>,referrer:Option<Address>)->i128{
 
-        let a_out = if swap_z_to_a { 
-            third_pool.swap_exact_input(&to, &true, &z_out, &a_min_out)
-        }else{
-            third_pool.swap_exact_input(&to, &false, &z_out, &a_min_out)
-        };
+        to.require_auth();
 
-        
-        a_out
+        swap_exact_input_path(&env, &to, &recipient, &pools, &vec![&env, swap_x_to_y, swap_y_to_z, swap_z_to_a], x_in, a_min_out, max_price_impact_bps, &referrer)
     }
 
-    fn swap_exact_input_quadruplehop(env:Env,to:Address,pools:Vec<Address>,swap_x_to_y:bool,swap_y_to_z:bool,swap_z_to_a:bool,swap_a_to_b:bool,x_in:i128, b_min_out:i128)->i128{
-        
+    fn swap_exact_input_quadruplehop(env:Env,to:Address,recipient:Address,pools:Vec<Address>,swap_x_to_y:bool,swap_y_to_z:bool,swap_z_to_a:bool,swap_a_to_b:bool,x_in:i128, b_min_out:i128,referrer:Option<Address>)->i128{
+
         to.require_auth();
 
-        let first_pool = liquiditypool::Client::new(&env, &pools.get(0).unwrap());
-        let second_pool = liquiditypool::Client::new(&env, &pools.get(1).unwrap());
-        let third_pool = liquiditypool::Client::new(&env, &pools.get(2).unwrap());
-        let fourth_pool = liquiditypool::Client::new(&env, &pools.get(3).unwrap());
+        swap_exact_input_path(&env, &to, &recipient, &pools, &vec![&env, swap_x_to_y, swap_y_to_z, swap_z_to_a, swap_a_to_b], x_in, b_min_out, None, &referrer)
+    }
 
-        let y_out = if swap_x_to_y {
-            first_pool.swap_exact_input(&to, &true, &x_in, &0)
-        }else{
-            first_pool.swap_exact_input(&to, &false, &x_in, &0)
-        };
+    fn swap_exact_in_fee_on_transfer(env:Env,to:Address,recipient:Address,pools:Vec<Address>,directions:Vec<bool>,x_in:i128,min_out:i128,max_price_impact_bps:Option<u32>,referrer:Option<Address>)->i128{
 
-        let z_out = if swap_y_to_z {
-            second_pool.swap_exact_input(&to, &true, &y_out, &0)
-        }else{
-            second_pool.swap_exact_input(&to, &false, &y_out, &0)
-        };
+        to.require_auth();
 
-        let a_out = if swap_z_to_a { 
-            third_pool.swap_exact_input(&to, &true, &z_out, &0)
-        }else{
-            third_pool.swap_exact_input(&to, &false, &z_out, &0)
-        };
+        swap_exact_input_path_supporting_fee_on_transfer(&env, &to, &recipient, &pools, &directions, x_in, min_out, max_price_impact_bps, &referrer)
+    }
 
-        let b_out = if swap_a_to_b { 
-            fourth_pool.swap_exact_input(&to, &true, &a_out, &b_min_out)
-        }else{
-            fourth_pool.swap_exact_input(&to, &false, &a_out, &b_min_out)
-        };
+    fn swap_exact_input_split(env:Env,to:Address,recipient:Address,routes:Vec<SwapRoute>,x_in:i128,min_out:i128,max_price_impact_bps:Option<u32>,referrer:Option<Address>)->i128{
+
+        to.require_auth();
+
+        assert!(!routes.is_empty(), "no routes given");
+        assert!(x_in > 0, "Amount must be grater then 0");
+
+        let first_route = routes.get(0).unwrap();
+        let (sell_token, buy_token) = get_route_tokens(&env, &first_route.pools, &first_route.directions);
+        for route in routes.iter() {
+            let route_tokens = get_route_tokens(&env, &route.pools, &route.directions);
+            assert!(route_tokens == (sell_token.clone(), buy_token.clone()), "routes must share input and output token");
+        }
+
+        token::Client::new(&env, &sell_token).transfer(&to, &env.current_contract_address(), &x_in);
+
+        let amounts_in = split_amount_in(&routes, x_in);
+        let mut amount_out = 0;
+        for (route, amount_in) in routes.iter().zip(amounts_in.iter()) {
+            if amount_in > 0 {
+                amount_out += swap_route_held_by_router(&env, &route.pools, &route.directions, amount_in, max_price_impact_bps, &referrer);
+            }
+        }
+
+        if amount_out < min_out {
+            panic!("Not satisfied minimum out")
+        }
+
+        token::Client::new(&env, &buy_token).transfer(&env.current_contract_address(), &recipient, &amount_out);
+
+        amount_out
+    }
 
-        
-        b_out
+    fn get_native_token(env:Env)->Address{
+        native_token(&env)
     }
 
-    
+    fn swap_exact_native_for_tokens(env:Env,to:Address,recipient:Address,token_out:Address,x_in:i128,min_out:i128,max_price_impact_bps:Option<u32>,referrer:Option<Address>)->i128{
+
+        to.require_auth();
+
+        swap_exact_input_pair(&env, &to, &recipient, native_token(&env), token_out, x_in, min_out, max_price_impact_bps, &referrer)
+    }
+
+    fn swap_exact_tokens_for_native(env:Env,to:Address,recipient:Address,token_in:Address,x_in:i128,min_out:i128,max_price_impact_bps:Option<u32>,referrer:Option<Address>)->i128{
+
+        to.require_auth();
+
+        swap_exact_input_pair(&env, &to, &recipient, token_in, native_token(&env), x_in, min_out, max_price_impact_bps, &referrer)
+    }
+
+
 }
@@ -0,0 +1,13 @@
+use soroban_sdk::{Address, Bytes, Env};
+
+// XDR of `Asset::Native`: just the ASSET_TYPE_NATIVE discriminant
+fn native_asset_xdr(e: &Env) -> Bytes {
+    Bytes::from_array(e, &[0u8; 4])
+}
+
+// Address of the Stellar Asset Contract wrapping native XLM on the current network
+pub fn native_token(e: &Env) -> Address {
+    e.deployer()
+        .with_stellar_asset(native_asset_xdr(e))
+        .deployed_address()
+}
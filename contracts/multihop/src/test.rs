@@ -0,0 +1,954 @@
+#![cfg(test)]
+extern crate std;
+
+use crate::{get_amount_out, liquiditypool, MultihopSwap, MultihopSwapClient, SwapRoute};
+
+use std::rc::Rc;
+
+use soroban_sdk::{
+    contract, contractimpl,
+    testutils::{Address as _, AuthorizedFunction, AuthorizedInvocation, BytesN as _},
+    token, vec,
+    xdr::{
+        AccountEntry, AccountEntryExt, AccountId, LedgerEntry, LedgerEntryData, LedgerEntryExt, LedgerKey,
+        LedgerKeyAccount, PublicKey, ScAddress, SequenceNumber, Thresholds, Uint256,
+    },
+    Address, Bytes, BytesN, Env, IntoVal, String, Symbol, TryFromVal, Vec,
+};
+
+mod token_contract {
+    soroban_sdk::contractimport!(
+        file = "../../target/wasm32-unknown-unknown/release/soroban_token_contract.wasm"
+    );
+}
+
+// Token that burns 1% of every transfer, standing in for community tokens with a transfer tax
+#[contract]
+pub struct TaxedToken;
+
+#[contractimpl]
+impl TaxedToken {
+    pub fn mint(e: Env, to: Address, amount: i128) {
+        let balance = Self::balance(e.clone(), to.clone());
+        e.storage().instance().set(&to, &(balance + amount));
+    }
+
+    pub fn balance(e: Env, id: Address) -> i128 {
+        e.storage().instance().get(&id).unwrap_or(0)
+    }
+
+    pub fn transfer(e: Env, from: Address, to: Address, amount: i128) {
+        from.require_auth();
+        let from_balance = Self::balance(e.clone(), from.clone());
+        let to_balance = Self::balance(e.clone(), to.clone());
+        assert!(from_balance >= amount, "insufficient balance");
+        e.storage().instance().set(&from, &(from_balance - amount));
+        e.storage().instance().set(&to, &(to_balance + amount - amount / 100));
+    }
+
+    pub fn symbol(e: Env) -> String {
+        String::from_slice(&e, "TAX")
+    }
+}
+
+fn deploy_native_token(e: &Env) -> Address {
+    e.deployer()
+        .with_stellar_asset(Bytes::from_array(e, &[0u8; 4]))
+        .deploy()
+}
+
+// Creates a classic account holding "amount" native XLM. The native asset has no admin that could
+// mint it, XLM balances live on the account entries themselves.
+fn create_native_account(e: &Env, amount: i64) -> Address {
+    let account_id = AccountId(PublicKey::PublicKeyTypeEd25519(Uint256(BytesN::<32>::random(e).to_array())));
+    let key = LedgerKey::Account(LedgerKeyAccount { account_id: account_id.clone() });
+    let entry = LedgerEntry {
+        last_modified_ledger_seq: 0,
+        data: LedgerEntryData::Account(AccountEntry {
+            account_id: account_id.clone(),
+            balance: amount,
+            seq_num: SequenceNumber(0),
+            num_sub_entries: 0,
+            inflation_dest: None,
+            flags: 0,
+            home_domain: Default::default(),
+            thresholds: Thresholds([1, 0, 0, 0]),
+            signers: Default::default(),
+            ext: AccountEntryExt::V0,
+        }),
+        ext: LedgerEntryExt::V0,
+    };
+    e.host().add_ledger_entry(&Rc::new(key), &Rc::new(entry), None).unwrap();
+
+    Address::try_from_val(e, &ScAddress::Account(account_id)).unwrap()
+}
+
+fn create_token_contract<'a>(e: &Env, admin: &Address) -> token_contract::Client<'a> {
+    token_contract::Client::new(e, &e.register_stellar_asset_contract(admin.clone()))
+}
+
+// Pool hooks that charge a flat 10% fee and veto swaps of more than 100_000
+#[contract]
+pub struct FeeHooks;
+
+#[contractimpl]
+impl FeeHooks {
+    pub fn before_deposit(_e: Env, _pool: Address, _from: Address, _to: Address, _amount_a: i128, _amount_b: i128) {}
+
+    pub fn after_deposit(_e: Env, _pool: Address, _from: Address, _to: Address, _amount_a: i128, _amount_b: i128, _shares: i128) {}
+
+    pub fn before_swap(_e: Env, _pool: Address, _from: Address, _sell_a: bool, amount: i128, _exact_input: bool, _fee_bps: i128) -> i128 {
+        if amount > 100_000 {
+            panic!("swap too large");
+        }
+        1000
+    }
+
+    pub fn after_swap(_e: Env, _pool: Address, _from: Address, _to: Address, _sell_a: bool, _amount_in: i128, _amount_out: i128) {}
+
+    pub fn before_withdraw(_e: Env, _pool: Address, _from: Address, _to: Address, _shares: i128) {}
+
+    pub fn after_withdraw(_e: Env, _pool: Address, _from: Address, _to: Address, _shares: i128, _amount_a: i128, _amount_b: i128) {}
+}
+
+// Asset contracts other than native need a trustline to credit an account, the pool's token contract doesn't
+fn create_account_token_contract<'a>(e: &Env, admin: &Address, symbol: &str) -> token_contract::Client<'a> {
+    let token = token_contract::Client::new(e, &e.register_contract_wasm(None, token_contract::WASM));
+    token.initialize(admin, &7, &String::from_slice(e, symbol), &String::from_slice(e, symbol));
+    token
+}
+
+fn create_router_contract<'a>(e: &Env, admin: &Address) -> MultihopSwapClient<'a> {
+    let router = MultihopSwapClient::new(e, &e.register_contract(None, MultihopSwap {}));
+    router.initialize(
+        admin,
+        &e.deployer().upload_contract_wasm(liquiditypool::WASM),
+        &e.deployer().upload_contract_wasm(token_contract::WASM),
+    );
+    router
+}
+
+// Deploys the pool of the pair through the router, seeds it from "lp" and registers it for routing
+fn create_pair<'a>(e: &Env, router: &MultihopSwapClient, lp: &Address, token_a: &Address, token_b: &Address, amount_a: i128, amount_b: i128) -> liquiditypool::Client<'a> {
+    router.add_liquidity(lp, token_a, token_b, &amount_a, &0, &amount_b, &0);
+    let pool = router.get_pool(token_a, token_b).unwrap();
+    router.register_pool(&pool);
+    liquiditypool::Client::new(e, &pool)
+}
+
+// Direction flag that sells "token" through the pool
+fn sells(pool: &liquiditypool::Client, token: &token_contract::Client) -> bool {
+    pool.get_tokens().0 == token.address
+}
+
+#[test]
+fn test_doublehop_pays_recipient_and_router_holds_intermediate() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let user = Address::random(&e);
+    let recipient = Address::random(&e);
+    let router = create_router_contract(&e, &admin);
+
+    let token_a = create_token_contract(&e, &admin);
+    let token_b = create_token_contract(&e, &admin);
+    let token_c = create_token_contract(&e, &admin);
+    for token in [&token_a, &token_b, &token_c] {
+        token.mint(&admin, &10_000_000);
+    }
+    token_a.mint(&user, &100_000);
+
+    let pool_ab = create_pair(&e, &router, &admin, &token_a.address, &token_b.address, 1_000_000, 2_000_000);
+    let pool_bc = create_pair(&e, &router, &admin, &token_b.address, &token_c.address, 2_000_000, 1_000_000);
+
+    let b_out = get_amount_out(10_000, 1_000_000, 2_000_000, 25);
+    let c_out = get_amount_out(b_out, 2_000_000, 1_000_000, 25);
+
+    let out = router.swap_exact_input_doublehop(
+        &user,
+        &recipient,
+        &vec![&e, pool_ab.address.clone(), pool_bc.address.clone()],
+        &sells(&pool_ab, &token_a),
+        &sells(&pool_bc, &token_b),
+        &10_000,
+        &c_out,
+        &None,
+        &None,
+    );
+    assert_eq!(out, c_out);
+
+    // Only the payer signs, the intermediate token never reaches the user
+    assert_eq!(e.auths().len(), 1);
+    assert_eq!(e.auths()[0].0, user);
+    assert_eq!(token_a.balance(&user), 90_000);
+    assert_eq!(token_b.balance(&user), 0);
+    assert_eq!(token_c.balance(&user), 0);
+    assert_eq!(token_c.balance(&recipient), c_out);
+    for token in [&token_a, &token_b, &token_c] {
+        assert_eq!(token.balance(&router.address), 0);
+    }
+}
+
+#[test]
+fn test_doublehop_pays_referrer_on_every_hop() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let user = Address::random(&e);
+    let referrer = Address::random(&e);
+    let router = create_router_contract(&e, &admin);
+
+    let token_a = create_token_contract(&e, &admin);
+    let token_b = create_token_contract(&e, &admin);
+    let token_c = create_token_contract(&e, &admin);
+    for token in [&token_a, &token_b, &token_c] {
+        token.mint(&admin, &10_000_000);
+    }
+    token_a.mint(&user, &100_000);
+
+    let pool_ab = create_pair(&e, &router, &admin, &token_a.address, &token_b.address, 1_000_000, 2_000_000);
+    let pool_bc = create_pair(&e, &router, &admin, &token_b.address, &token_c.address, 2_000_000, 1_000_000);
+    pool_ab.set_referral_share(&2000);
+    pool_bc.set_referral_share(&2000);
+
+    // The referral comes out of the fee, the amounts swapped are unchanged
+    let b_out = get_amount_out(10_000, 1_000_000, 2_000_000, 25);
+    let c_out = get_amount_out(b_out, 2_000_000, 1_000_000, 25);
+    let referral_a = 10_000 * 25 / 10000 * 2000 / 10000;
+    let referral_b = b_out * 25 / 10000 * 2000 / 10000;
+
+    let out = router.swap_exact_input_doublehop(
+        &user,
+        &user,
+        &vec![&e, pool_ab.address.clone(), pool_bc.address.clone()],
+        &sells(&pool_ab, &token_a),
+        &sells(&pool_bc, &token_b),
+        &10_000,
+        &c_out,
+        &None,
+        &Some(referrer.clone()),
+    );
+    assert_eq!(out, c_out);
+
+    // Each hop pays the referrer in the token it sold
+    assert_eq!(token_a.balance(&referrer), referral_a);
+    assert_eq!(token_b.balance(&referrer), referral_b);
+    assert_eq!(token_c.balance(&referrer), 0);
+
+    // The LPs keep the rest of the fee
+    let rsrvs = |pool: &liquiditypool::Client, first: &token_contract::Client, reserve_first: i128, reserve_second: i128| {
+        if sells(pool, first) { (reserve_first, reserve_second) } else { (reserve_second, reserve_first) }
+    };
+    assert_eq!(pool_ab.get_rsrvs(), rsrvs(&pool_ab, &token_a, 1_000_000 + 10_000 - referral_a, 2_000_000 - b_out));
+    assert_eq!(pool_bc.get_rsrvs(), rsrvs(&pool_bc, &token_b, 2_000_000 + b_out - referral_b, 1_000_000 - c_out));
+    for token in [&token_a, &token_b, &token_c] {
+        assert_eq!(token.balance(&router.address), 0);
+    }
+}
+
+// Routes 10_000 of token A through a deep A/B pool and a ten times shallower B/C pool. Returns the
+// router, the payer, the route and its price impact in basis points.
+fn create_impact_route<'a>(e: &Env) -> (MultihopSwapClient<'a>, Address, Vec<Address>, bool, bool, u32) {
+    let admin = Address::random(e);
+    let router = create_router_contract(e, &admin);
+
+    let token_a = create_token_contract(e, &admin);
+    let token_b = create_token_contract(e, &admin);
+    let token_c = create_token_contract(e, &admin);
+    for token in [&token_a, &token_b, &token_c] {
+        token.mint(&admin, &10_000_000);
+    }
+
+    // The second pool sets the worst impact of the route
+    let pool_ab = create_pair(e, &router, &admin, &token_a.address, &token_b.address, 1_000_000, 1_000_000);
+    let pool_bc = create_pair(e, &router, &admin, &token_b.address, &token_c.address, 100_000, 100_000);
+    let pools = vec![e, pool_ab.address.clone(), pool_bc.address.clone()];
+    let (dir_ab, dir_bc) = (sells(&pool_ab, &token_a), sells(&pool_bc, &token_b));
+
+    let b_out = get_amount_out(10_000, 1_000_000, 1_000_000, 25);
+    let c_out = get_amount_out(b_out, 100_000, 100_000, 25);
+    let impact = (10000 - (100_000 - c_out) * 100_000 * 10000 / (100_000 * (100_000 + b_out))) as u32;
+
+    (router, admin, pools, dir_ab, dir_bc, impact)
+}
+
+#[test]
+fn test_doublehop_price_impact_within_cap() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let (router, admin, pools, dir_ab, dir_bc, impact) = create_impact_route(&e);
+    let b_out = get_amount_out(10_000, 1_000_000, 1_000_000, 25);
+
+    let out = router.swap_exact_input_doublehop(&admin, &admin, &pools, &dir_ab, &dir_bc, &10_000, &0, &Some(impact), &None);
+    assert_eq!(out, get_amount_out(b_out, 100_000, 100_000, 25));
+}
+
+// The host of the pinned SDK can't roll back a failed call once the router authorized a pool's transfer,
+// so rejected routes are tested as panics rather than through the try_ client
+#[test]
+#[should_panic]
+fn test_doublehop_price_impact_above_cap() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let (router, admin, pools, dir_ab, dir_bc, impact) = create_impact_route(&e);
+    router.swap_exact_input_doublehop(&admin, &admin, &pools, &dir_ab, &dir_bc, &10_000, &0, &Some(impact - 1), &None);
+}
+
+#[test]
+fn test_triplehop_and_quadruplehop() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let user = Address::random(&e);
+    let router = create_router_contract(&e, &admin);
+
+    let token_a = create_token_contract(&e, &admin);
+    let token_b = create_token_contract(&e, &admin);
+    let token_c = create_token_contract(&e, &admin);
+    let token_d = create_token_contract(&e, &admin);
+    for token in [&token_a, &token_b, &token_c, &token_d] {
+        token.mint(&admin, &10_000_000);
+    }
+    token_a.mint(&user, &100_000);
+
+    let pool_ab = create_pair(&e, &router, &admin, &token_a.address, &token_b.address, 1_000_000, 1_000_000);
+    let pool_bc = create_pair(&e, &router, &admin, &token_b.address, &token_c.address, 1_000_000, 1_000_000);
+    let pool_cd = create_pair(&e, &router, &admin, &token_c.address, &token_d.address, 1_000_000, 1_000_000);
+    let pool_da = create_pair(&e, &router, &admin, &token_d.address, &token_a.address, 1_000_000, 1_000_000);
+
+    let mut expected = 10_000;
+    for _ in 0..3 {
+        expected = get_amount_out(expected, 1_000_000, 1_000_000, 25);
+    }
+    let d_out = router.swap_exact_input_triplehop(
+        &user,
+        &user,
+        &vec![&e, pool_ab.address.clone(), pool_bc.address.clone(), pool_cd.address.clone()],
+        &sells(&pool_ab, &token_a),
+        &sells(&pool_bc, &token_b),
+        &sells(&pool_cd, &token_c),
+        &10_000,
+        &0,
+        &None,
+        &None,
+    );
+    assert_eq!(d_out, expected);
+    assert_eq!(token_d.balance(&user), d_out);
+
+    // A full cycle back to the input token only loses fees and price impact
+    let a_out = router.swap_exact_input_quadruplehop(
+        &user,
+        &user,
+        &vec![&e, pool_ab.address.clone(), pool_bc.address.clone(), pool_cd.address.clone(), pool_da.address.clone()],
+        &sells(&pool_ab, &token_a),
+        &sells(&pool_bc, &token_b),
+        &sells(&pool_cd, &token_c),
+        &sells(&pool_da, &token_d),
+        &10_000,
+        &0,
+        &None,
+    );
+    assert!(a_out > 0 && a_out < 10_000);
+    assert_eq!(token_a.balance(&user), 80_000 + a_out);
+    assert_eq!(token_b.balance(&router.address), 0);
+    assert_eq!(token_c.balance(&router.address), 0);
+}
+
+#[test]
+#[should_panic(expected = "Not satisfied minimum out")]
+fn test_doublehop_minimum_out() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let router = create_router_contract(&e, &admin);
+
+    let token_a = create_token_contract(&e, &admin);
+    let token_b = create_token_contract(&e, &admin);
+    let token_c = create_token_contract(&e, &admin);
+    for token in [&token_a, &token_b, &token_c] {
+        token.mint(&admin, &10_000_000);
+    }
+
+    let pool_ab = create_pair(&e, &router, &admin, &token_a.address, &token_b.address, 1_000_000, 1_000_000);
+    let pool_bc = create_pair(&e, &router, &admin, &token_b.address, &token_c.address, 1_000_000, 1_000_000);
+
+    let c_out = get_amount_out(get_amount_out(10_000, 1_000_000, 1_000_000, 25), 1_000_000, 1_000_000, 25);
+    router.swap_exact_input_doublehop(
+        &admin,
+        &admin,
+        &vec![&e, pool_ab.address.clone(), pool_bc.address.clone()],
+        &sells(&pool_ab, &token_a),
+        &sells(&pool_bc, &token_b),
+        &10_000,
+        &(c_out + 1),
+        &None,
+        &None,
+    );
+}
+
+#[test]
+fn test_split_swap_aggregates_routes() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let user = Address::random(&e);
+    let router = create_router_contract(&e, &admin);
+
+    let token_a = create_token_contract(&e, &admin);
+    let token_b = create_token_contract(&e, &admin);
+    let token_c = create_token_contract(&e, &admin);
+    for token in [&token_a, &token_b, &token_c] {
+        token.mint(&admin, &10_000_000);
+    }
+    token_a.mint(&user, &100_000);
+
+    let pool_ac = create_pair(&e, &router, &admin, &token_a.address, &token_c.address, 1_000_000, 1_000_000);
+    let pool_ab = create_pair(&e, &router, &admin, &token_a.address, &token_b.address, 1_000_000, 1_000_000);
+    let pool_bc = create_pair(&e, &router, &admin, &token_b.address, &token_c.address, 1_000_000, 1_000_000);
+
+    // 60% direct and 40% through token_b. 60% of 99_999 rounds down to 59_999, the last route takes the remaining 40_000.
+    let routes = vec![
+        &e,
+        SwapRoute {
+            pools: vec![&e, pool_ac.address.clone()],
+            directions: vec![&e, sells(&pool_ac, &token_a)],
+            fraction_bps: 6000,
+        },
+        SwapRoute {
+            pools: vec![&e, pool_ab.address.clone(), pool_bc.address.clone()],
+            directions: vec![&e, sells(&pool_ab, &token_a), sells(&pool_bc, &token_b)],
+            fraction_bps: 4000,
+        },
+    ];
+    let direct_out = get_amount_out(59_999, 1_000_000, 1_000_000, 25);
+    let hop_out = get_amount_out(get_amount_out(40_000, 1_000_000, 1_000_000, 25), 1_000_000, 1_000_000, 25);
+
+    let out = router.swap_exact_input_split(&user, &user, &routes, &99_999, &(direct_out + hop_out), &None, &None);
+    assert_eq!(out, direct_out + hop_out);
+    assert_eq!(token_a.balance(&user), 1);
+    assert_eq!(token_c.balance(&user), out);
+    assert_eq!(pool_ac.get_rsrvs(), if sells(&pool_ac, &token_a) { (1_059_999, 1_000_000 - direct_out) } else { (1_000_000 - direct_out, 1_059_999) });
+    assert_eq!(token_b.balance(&router.address), 0);
+}
+
+#[test]
+#[should_panic(expected = "route fractions must sum to 10000")]
+fn test_split_swap_fractions_must_cover_input() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let router = create_router_contract(&e, &admin);
+
+    let token_a = create_token_contract(&e, &admin);
+    let token_b = create_token_contract(&e, &admin);
+    token_a.mint(&admin, &10_000_000);
+    token_b.mint(&admin, &10_000_000);
+    let pool = create_pair(&e, &router, &admin, &token_a.address, &token_b.address, 1_000_000, 1_000_000);
+
+    let route = SwapRoute {
+        pools: vec![&e, pool.address.clone()],
+        directions: vec![&e, sells(&pool, &token_a)],
+        fraction_bps: 4000,
+    };
+    router.swap_exact_input_split(&admin, &admin, &vec![&e, route.clone(), route], &10_000, &0, &None, &None);
+}
+
+#[test]
+#[should_panic(expected = "routes must share input and output token")]
+fn test_split_swap_routes_must_share_tokens() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let router = create_router_contract(&e, &admin);
+
+    let token_a = create_token_contract(&e, &admin);
+    let token_b = create_token_contract(&e, &admin);
+    let token_c = create_token_contract(&e, &admin);
+    for token in [&token_a, &token_b, &token_c] {
+        token.mint(&admin, &10_000_000);
+    }
+    let pool_ab = create_pair(&e, &router, &admin, &token_a.address, &token_b.address, 1_000_000, 1_000_000);
+    let pool_ac = create_pair(&e, &router, &admin, &token_a.address, &token_c.address, 1_000_000, 1_000_000);
+
+    let routes = vec![
+        &e,
+        SwapRoute { pools: vec![&e, pool_ab.address.clone()], directions: vec![&e, sells(&pool_ab, &token_a)], fraction_bps: 5000 },
+        SwapRoute { pools: vec![&e, pool_ac.address.clone()], directions: vec![&e, sells(&pool_ac, &token_a)], fraction_bps: 5000 },
+    ];
+    router.swap_exact_input_split(&admin, &admin, &routes, &10_000, &0, &None, &None);
+}
+
+#[test]
+#[should_panic(expected = "route hops are not chained")]
+fn test_doublehop_hops_must_be_chained() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let router = create_router_contract(&e, &admin);
+
+    let token_a = create_token_contract(&e, &admin);
+    let token_b = create_token_contract(&e, &admin);
+    let token_c = create_token_contract(&e, &admin);
+    for token in [&token_a, &token_b, &token_c] {
+        token.mint(&admin, &10_000_000);
+    }
+    let pool_ab = create_pair(&e, &router, &admin, &token_a.address, &token_b.address, 1_000_000, 1_000_000);
+    let pool_ac = create_pair(&e, &router, &admin, &token_a.address, &token_c.address, 1_000_000, 1_000_000);
+
+    // The first hop buys B, the second sells A
+    let pools = vec![&e, pool_ab.address.clone(), pool_ac.address.clone()];
+    router.swap_exact_input_doublehop(&admin, &admin, &pools, &sells(&pool_ab, &token_a), &sells(&pool_ac, &token_a), &10_000, &0, &None, &None);
+}
+
+#[test]
+#[should_panic(expected = "route hops are not chained")]
+fn test_split_swap_route_hops_must_be_chained() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let router = create_router_contract(&e, &admin);
+
+    let token_a = create_token_contract(&e, &admin);
+    let token_b = create_token_contract(&e, &admin);
+    let token_c = create_token_contract(&e, &admin);
+    for token in [&token_a, &token_b, &token_c] {
+        token.mint(&admin, &10_000_000);
+    }
+    let pool_ab = create_pair(&e, &router, &admin, &token_a.address, &token_b.address, 1_000_000, 1_000_000);
+    let pool_ac = create_pair(&e, &router, &admin, &token_a.address, &token_c.address, 1_000_000, 1_000_000);
+
+    // Both routes sell A for C, but the second one's hops don't connect
+    let routes = vec![
+        &e,
+        SwapRoute { pools: vec![&e, pool_ac.address.clone()], directions: vec![&e, sells(&pool_ac, &token_a)], fraction_bps: 5000 },
+        SwapRoute {
+            pools: vec![&e, pool_ab.address.clone(), pool_ac.address.clone()],
+            directions: vec![&e, sells(&pool_ab, &token_a), sells(&pool_ac, &token_a)],
+            fraction_bps: 5000,
+        },
+    ];
+    router.swap_exact_input_split(&admin, &admin, &routes, &10_000, &0, &None, &None);
+}
+
+#[test]
+fn test_multihop_fee_on_transfer() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let user = Address::random(&e);
+    let recipient = Address::random(&e);
+    let router = create_router_contract(&e, &admin);
+
+    let taxed = TaxedTokenClient::new(&e, &e.register_contract(None, TaxedToken {}));
+    let token_b = create_token_contract(&e, &admin);
+    let token_c = create_token_contract(&e, &admin);
+    taxed.mint(&admin, &10_000_000);
+    token_b.mint(&admin, &10_000_000);
+    token_c.mint(&admin, &10_000_000);
+    taxed.mint(&user, &100_000);
+
+    router.add_liquidity(&admin, &taxed.address, &token_b.address, &1_000_000, &0, &1_000_000, &0);
+    let pool_ab = liquiditypool::Client::new(&e, &router.get_pool(&taxed.address, &token_b.address).unwrap());
+    let pool_bc = create_pair(&e, &router, &admin, &token_b.address, &token_c.address, 1_000_000, 1_000_000);
+    let sell_taxed = pool_ab.get_tokens().0 == taxed.address;
+    assert_eq!(pool_ab.get_rsrvs(), if sell_taxed { (990_000, 1_000_000) } else { (1_000_000, 990_000) });
+
+    // 10_000 is taxed on the way to the router and again on the way to the pool
+    let b_out = get_amount_out(9_801, 990_000, 1_000_000, 25);
+    let c_out = get_amount_out(b_out, 1_000_000, 1_000_000, 25);
+
+    let out = router.swap_exact_in_fee_on_transfer(
+        &user,
+        &recipient,
+        &vec![&e, pool_ab.address.clone(), pool_bc.address.clone()],
+        &vec![&e, sell_taxed, sells(&pool_bc, &token_b)],
+        &10_000,
+        &c_out,
+        &None,
+        &None,
+    );
+    assert_eq!(out, c_out);
+    assert_eq!(token_c.balance(&recipient), c_out);
+    assert_eq!(taxed.balance(&user), 90_000);
+    assert_eq!(taxed.balance(&router.address), 0);
+    assert_eq!(token_b.balance(&router.address), 0);
+
+    // Selling into the taxed token, the pool's payout is taxed once to the router and again to the recipient
+    token_c.mint(&user, &10_000);
+    let (reserve_c, reserve_b) = if sells(&pool_bc, &token_c) { pool_bc.get_rsrvs() } else { let (b, c) = pool_bc.get_rsrvs(); (c, b) };
+    let (reserve_taxed, reserve_b_ab) = if sell_taxed { pool_ab.get_rsrvs() } else { let (b, a) = pool_ab.get_rsrvs(); (a, b) };
+    let pool_out = get_amount_out(get_amount_out(10_000, reserve_c, reserve_b, 25), reserve_b_ab, reserve_taxed, 25);
+    let router_received = pool_out - pool_out / 100;
+    let received = router_received - router_received / 100;
+
+    let out = router.swap_exact_in_fee_on_transfer(
+        &user,
+        &user,
+        &vec![&e, pool_bc.address.clone(), pool_ab.address.clone()],
+        &vec![&e, sells(&pool_bc, &token_c), !sell_taxed],
+        &10_000,
+        &received,
+        &None,
+        &None,
+    );
+    assert_eq!(out, received);
+    assert_eq!(taxed.balance(&user), 90_000 + received);
+}
+
+#[test]
+fn test_find_best_route_quotes_hook_fees() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let router = create_router_contract(&e, &admin);
+
+    let token_a = create_token_contract(&e, &admin);
+    let token_b = create_token_contract(&e, &admin);
+    let token_c = create_token_contract(&e, &admin);
+    for token in [&token_a, &token_b, &token_c] {
+        token.mint(&admin, &10_000_000);
+    }
+
+    // The direct pool reports the usual 0.25% from get_fee, its hooks charge 10%
+    let hooks = e.register_contract(None, FeeHooks {});
+    let pool_ac = liquiditypool::Client::new(&e, &e.register_contract_wasm(None, liquiditypool::WASM));
+    pool_ac.initialize(
+        &e.deployer().upload_contract_wasm(token_contract::WASM),
+        &token_a.address,
+        &token_c.address,
+        &None,
+        &None,
+        &admin,
+        &Some(hooks),
+    );
+    pool_ac.deposit(&admin, &admin, &1_000_000, &0, &1_000_000, &0);
+    router.register_pool(&pool_ac.address);
+    assert_eq!(pool_ac.get_fee(), 25);
+
+    let pool_ab = create_pair(&e, &router, &admin, &token_a.address, &token_b.address, 1_000_000, 1_000_000);
+    let pool_bc = create_pair(&e, &router, &admin, &token_b.address, &token_c.address, 1_000_000, 1_000_000);
+
+    // At 0.25% the direct pool would win, at its real fee the path through token_b does
+    let route = router.find_best_route(&token_a.address, &token_c.address, &10_000, &4);
+    assert_eq!(route.pools, vec![&e, pool_ab.address.clone(), pool_bc.address.clone()]);
+
+    // The direct quote matches what the pool charges
+    let route = router.find_best_route(&token_a.address, &token_c.address, &10_000, &1);
+    assert_eq!(route.pools, vec![&e, pool_ac.address.clone()]);
+    assert_eq!(route.amount_out, get_amount_out(10_000, 1_000_000, 1_000_000, 1000));
+    let out = router.swap_exact_input_split(
+        &admin,
+        &admin,
+        &vec![&e, SwapRoute { pools: route.pools.clone(), directions: route.directions.clone(), fraction_bps: 10000 }],
+        &10_000,
+        &route.amount_out,
+        &None,
+        &None,
+    );
+    assert_eq!(out, route.amount_out);
+
+    // Pools whose hooks would veto the swap aren't routed through
+    let route = router.find_best_route(&token_a.address, &token_c.address, &200_000, &1);
+    assert_eq!(route.pools, vec![&e]);
+    assert_eq!(route.amount_out, 0);
+}
+
+#[test]
+fn test_find_best_route() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let router = create_router_contract(&e, &admin);
+
+    let token_a = create_token_contract(&e, &admin);
+    let token_b = create_token_contract(&e, &admin);
+    let token_c = create_token_contract(&e, &admin);
+    let token_d = create_token_contract(&e, &admin);
+    for token in [&token_a, &token_b, &token_c, &token_d] {
+        token.mint(&admin, &10_000_000);
+    }
+
+    // A shallow direct pool next to a deep path through token_b
+    let pool_ac = create_pair(&e, &router, &admin, &token_a.address, &token_c.address, 100_000, 100_000);
+    let pool_ab = create_pair(&e, &router, &admin, &token_a.address, &token_b.address, 1_000_000, 1_000_000);
+    let pool_bc = create_pair(&e, &router, &admin, &token_b.address, &token_c.address, 1_000_000, 1_000_000);
+
+    // Small trades take the direct pool, paying the fee once
+    let route = router.find_best_route(&token_a.address, &token_c.address, &100, &4);
+    assert_eq!(route.pools, vec![&e, pool_ac.address.clone()]);
+    assert_eq!(route.directions, vec![&e, sells(&pool_ac, &token_a)]);
+    assert_eq!(route.amount_out, get_amount_out(100, 100_000, 100_000, 25));
+
+    // Large trades avoid the price impact of the shallow pool
+    let route = router.find_best_route(&token_a.address, &token_c.address, &10_000, &4);
+    assert_eq!(route.pools, vec![&e, pool_ab.address.clone(), pool_bc.address.clone()]);
+    assert_eq!(route.directions, vec![&e, sells(&pool_ab, &token_a), sells(&pool_bc, &token_b)]);
+    assert_eq!(route.amount_out, get_amount_out(get_amount_out(10_000, 1_000_000, 1_000_000, 25), 1_000_000, 1_000_000, 25));
+
+    // The quoted route executes for the quoted amount
+    let out = router.swap_exact_input_doublehop(
+        &admin,
+        &admin,
+        &route.pools,
+        &route.directions.get(0).unwrap(),
+        &route.directions.get(1).unwrap(),
+        &10_000,
+        &route.amount_out,
+        &None,
+        &None,
+    );
+    assert_eq!(out, route.amount_out);
+
+    // max_hops limits the search to the direct pool
+    let route = router.find_best_route(&token_a.address, &token_c.address, &10_000, &1);
+    assert_eq!(route.pools, vec![&e, pool_ac.address.clone()]);
+
+    // No pool trades token_d
+    let route = router.find_best_route(&token_a.address, &token_d.address, &10_000, &4);
+    assert_eq!(route.amount_out, 0);
+    assert!(route.pools.is_empty());
+}
+
+#[test]
+fn test_add_and_remove_liquidity_in_caller_token_order() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let user = Address::random(&e);
+    let router = create_router_contract(&e, &admin);
+
+    let token_a = create_token_contract(&e, &admin);
+    let token_b = create_token_contract(&e, &admin);
+    token_a.mint(&user, &10_000_000);
+    token_b.mint(&user, &10_000_000);
+
+    let (shares, amount_b, amount_a) =
+        router.add_liquidity(&user, &token_b.address, &token_a.address, &2_000_000, &0, &1_000_000, &0);
+    assert_eq!((amount_a, amount_b), (1_000_000, 2_000_000));
+    assert_eq!(token_a.balance(&user), 9_000_000);
+    assert_eq!(token_b.balance(&user), 8_000_000);
+
+    // Both token orders resolve to the same pool
+    let pool = router.get_pool(&token_a.address, &token_b.address).unwrap();
+    assert_eq!(router.get_pool(&token_b.address, &token_a.address), Some(pool.clone()));
+    let pool = liquiditypool::Client::new(&e, &pool);
+    assert_eq!(pool.get_lptoken_balance(&user), shares);
+
+    // A second deposit reuses the pool and is matched to its price
+    let (_, amount_a, amount_b) =
+        router.add_liquidity(&user, &token_a.address, &token_b.address, &100_000, &0, &500_000, &0);
+    assert_eq!((amount_a, amount_b), (100_000, 200_000));
+
+    // The first deposit's shares are short of the locked minimum liquidity
+    let (amount_b, amount_a) = router.remove_liquidity(&user, &token_b.address, &token_a.address, &shares, &0, &0);
+    assert!(amount_a < 1_000_000 && amount_b == 2 * amount_a);
+    assert_eq!(token_a.balance(&user), 8_900_000 + amount_a);
+    assert_eq!(token_b.balance(&user), 7_800_000 + amount_b);
+}
+
+#[test]
+#[should_panic(expected = "Pool does not exist")]
+fn test_remove_liquidity_unknown_pair() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let router = create_router_contract(&e, &admin);
+    let token_a = create_token_contract(&e, &admin);
+    let token_b = create_token_contract(&e, &admin);
+
+    router.remove_liquidity(&admin, &token_a.address, &token_b.address, &1000, &0, &0);
+}
+
+#[test]
+fn test_deployed_pairs_are_routed_once_registered() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let user = Address::random(&e);
+    let router = create_router_contract(&e, &admin);
+
+    let token_a = create_token_contract(&e, &admin);
+    let token_b = create_token_contract(&e, &admin);
+    token_a.mint(&user, &10_000_000);
+    token_b.mint(&user, &10_000_000);
+
+    // Anyone can deploy a pair, it doesn't affect routing
+    router.add_liquidity(&user, &token_a.address, &token_b.address, &1_000_000, &0, &1_000_000, &0);
+    let pool = router.get_pool(&token_a.address, &token_b.address).unwrap();
+    assert!(router.get_paired_tokens(&token_a.address).is_empty());
+    assert_eq!(router.find_best_route(&token_a.address, &token_b.address, &10_000, &4).amount_out, 0);
+
+    router.register_pool(&pool);
+    assert_eq!(
+        e.auths(),
+        std::vec![(
+            admin.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    router.address.clone(),
+                    Symbol::new(&e, "register_pool"),
+                    (&pool,).into_val(&e),
+                )),
+                sub_invocations: std::vec![]
+            }
+        )]
+    );
+    assert_eq!(router.get_paired_tokens(&token_a.address), vec![&e, token_b.address.clone()]);
+    assert_eq!(router.get_paired_tokens(&token_b.address), vec![&e, token_a.address.clone()]);
+
+    let route = router.find_best_route(&token_a.address, &token_b.address, &10_000, &4);
+    assert_eq!(route.pools, vec![&e, pool]);
+    assert_eq!(route.amount_out, get_amount_out(10_000, 1_000_000, 1_000_000, 25));
+}
+
+#[test]
+#[should_panic(expected = "Pair already has a pool")]
+fn test_register_second_pool_for_pair() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let router = create_router_contract(&e, &admin);
+    let other_router = create_router_contract(&e, &admin);
+
+    let token_a = create_token_contract(&e, &admin);
+    let token_b = create_token_contract(&e, &admin);
+    token_a.mint(&admin, &10_000_000);
+    token_b.mint(&admin, &10_000_000);
+
+    create_pair(&e, &router, &admin, &token_a.address, &token_b.address, 1_000_000, 1_000_000);
+    let other = create_pair(&e, &other_router, &admin, &token_a.address, &token_b.address, 1_000_000, 1_000_000);
+
+    router.register_pool(&other.address);
+}
+
+#[test]
+fn test_native_token_is_stellar_asset_contract() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    let router = create_router_contract(&e, &Address::random(&e));
+
+    let native = deploy_native_token(&e);
+
+    assert_eq!(router.get_native_token(), native);
+    assert_eq!(token::Client::new(&e, &native).symbol(), String::from_slice(&e, "native"));
+}
+
+#[test]
+fn test_native_pool_through_router() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let user = create_native_account(&e, 10_000_000);
+    let router = create_router_contract(&e, &admin);
+
+    let native = deploy_native_token(&e);
+    let usdc = create_account_token_contract(&e, &admin, "USDC");
+    let native_client = token::Client::new(&e, &native);
+
+    usdc.mint(&user, &10_000_000);
+
+    let (shares, amount_native, amount_usdc) =
+        router.add_liquidity(&user, &native, &usdc.address, &1_000_000, &0, &2_000_000, &0);
+    assert_eq!((amount_native, amount_usdc), (1_000_000, 2_000_000));
+    assert!(shares > 0);
+
+    let pool = router.get_pool(&usdc.address, &native).unwrap();
+    assert_eq!(liquiditypool::Client::new(&e, &pool).get_lptoken_balance(&user), shares);
+
+    let usdc_out = router.swap_exact_native_for_tokens(&user, &user, &usdc.address, &10_000, &0, &None, &None);
+    assert!(usdc_out > 0);
+    assert_eq!(usdc.balance(&user), 8_000_000 + usdc_out);
+    assert_eq!(native_client.balance(&user), 9_000_000 - 10_000);
+
+    let native_out = router.swap_exact_tokens_for_native(&user, &user, &usdc.address, &usdc_out, &0, &None, &None);
+    assert!(native_out > 0 && native_out < 10_000);
+    assert_eq!(native_client.balance(&user), 9_000_000 - 10_000 + native_out);
+    assert_eq!(native_client.balance(&router.address), 0);
+}
+
+// Permissioned pool with "member" allowed and holding 100_000 of token A, deployed through "router"
+fn create_permissioned_pair<'a>(e: &Env, router: &MultihopSwapClient, admin: &Address, member: &Address) -> (liquiditypool::Client<'a>, token_contract::Client<'a>, token_contract::Client<'a>) {
+    let token_a = create_account_token_contract(e, admin, "AAA");
+    let token_b = create_account_token_contract(e, admin, "BBB");
+    for token in [&token_a, &token_b] {
+        token.mint(admin, &10_000_000);
+    }
+    token_a.mint(member, &100_000);
+
+    let pool = create_pair(e, router, admin, &token_a.address, &token_b.address, 1_000_000, 1_000_000);
+    pool.set_permissioned(&true);
+    pool.set_allowed(member, &true);
+
+    (pool, token_a, token_b)
+}
+
+#[test]
+fn test_permissioned_pool_trades_directly() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let member = create_native_account(&e, 0);
+    let router = create_router_contract(&e, &admin);
+    let (pool, token_a, token_b) = create_permissioned_pair(&e, &router, &admin, &member);
+
+    // Allowed accounts trade on the pool directly
+    let out = pool.swap_exact_input(&member, &member, &sells(&pool, &token_a), &10_000, &0, &None, &None);
+    assert_eq!(token_b.balance(&member), out);
+}
+
+// The router isn't on the allowlist, so a permissioned pool rejects trades routed through it
+#[test]
+#[should_panic]
+fn test_permissioned_pool_is_not_reachable_through_router() {
+    let e = Env::default();
+    e.budget().reset_unlimited();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let member = create_native_account(&e, 0);
+    let router = create_router_contract(&e, &admin);
+    let (pool, token_a, _) = create_permissioned_pair(&e, &router, &admin, &member);
+
+    let route = SwapRoute { pools: vec![&e, pool.address.clone()], directions: vec![&e, sells(&pool, &token_a)], fraction_bps: 10000 };
+    router.swap_exact_input_split(&member, &member, &vec![&e, route], &10_000, &0, &None, &None);
+}
@@ -0,0 +1,34 @@
+use crate::storage_types::{DataKey, BALANCE_BUMP_AMOUNT, BALANCE_LIFETIME_THRESHOLD};
+use soroban_sdk::{Address, Env};
+
+pub fn read_auth_required(e: &Env) -> bool {
+    e.storage().instance().get(&DataKey::AuthRequired).unwrap_or(false)
+}
+
+pub fn write_auth_required(e: &Env, required: bool) {
+    e.storage().instance().set(&DataKey::AuthRequired, &required);
+}
+
+pub fn read_authorized(e: &Env, addr: Address) -> bool {
+    let key = DataKey::Authorized(addr);
+    if let Some(authorized) = e.storage().persistent().get::<DataKey, bool>(&key) {
+        e.storage()
+            .persistent()
+            .bump(&key, BALANCE_LIFETIME_THRESHOLD, BALANCE_BUMP_AMOUNT);
+        authorized
+    } else {
+        false
+    }
+}
+
+pub fn write_authorized(e: &Env, addr: Address, authorize: bool) {
+    let key = DataKey::Authorized(addr);
+    if authorize {
+        e.storage().persistent().set(&key, &true);
+        e.storage()
+            .persistent()
+            .bump(&key, BALANCE_LIFETIME_THRESHOLD, BALANCE_BUMP_AMOUNT);
+    } else {
+        e.storage().persistent().remove(&key);
+    }
+}
@@ -0,0 +1,118 @@
+use crate::storage_types::{Checkpoint, DataKey, BALANCE_BUMP_AMOUNT, BALANCE_LIFETIME_THRESHOLD};
+use soroban_sdk::{Address, Env};
+
+// Each checkpoint is kept under its own key, "checkpoint_key(idx)", and "count_key" holds how many
+// there are, so a write touches one entry whatever the length of the history.
+fn read_count(e: &Env, count_key: &DataKey) -> u32 {
+    if let Some(count) = e.storage().persistent().get::<DataKey, u32>(count_key) {
+        e.storage()
+            .persistent()
+            .bump(count_key, BALANCE_LIFETIME_THRESHOLD, BALANCE_BUMP_AMOUNT);
+        count
+    } else {
+        0
+    }
+}
+
+fn read_checkpoint(e: &Env, key: &DataKey) -> Checkpoint {
+    let checkpoint = e.storage().persistent().get(key).unwrap();
+    e.storage()
+        .persistent()
+        .bump(key, BALANCE_LIFETIME_THRESHOLD, BALANCE_BUMP_AMOUNT);
+    checkpoint
+}
+
+// Records "amount" as the value at the end of the current ledger, overwriting an earlier
+// checkpoint of the same ledger so there is at most one per ledger
+fn write_checkpoint(
+    e: &Env,
+    count_key: DataKey,
+    checkpoint_key: impl Fn(u32) -> DataKey,
+    amount: i128,
+) {
+    let ledger = e.ledger().sequence();
+    let count = read_count(e, &count_key);
+
+    let idx = if count > 0 && read_checkpoint(e, &checkpoint_key(count - 1)).ledger == ledger {
+        count - 1
+    } else {
+        e.storage().persistent().set(&count_key, &(count + 1));
+        e.storage()
+            .persistent()
+            .bump(&count_key, BALANCE_LIFETIME_THRESHOLD, BALANCE_BUMP_AMOUNT);
+        count
+    };
+
+    let key = checkpoint_key(idx);
+    e.storage()
+        .persistent()
+        .set(&key, &Checkpoint { ledger, amount });
+    e.storage()
+        .persistent()
+        .bump(&key, BALANCE_LIFETIME_THRESHOLD, BALANCE_BUMP_AMOUNT);
+}
+
+// Value at the end of "ledger": the latest checkpoint at or before it, 0 before the first one.
+// Only past ledgers can be read, the current one may still change.
+fn read_checkpoint_at(
+    e: &Env,
+    count_key: DataKey,
+    checkpoint_key: impl Fn(u32) -> DataKey,
+    ledger: u32,
+) -> i128 {
+    if ledger >= e.ledger().sequence() {
+        panic!("ledger is not finalized yet")
+    }
+
+    let (mut low, mut high) = (0, read_count(e, &count_key));
+    while low < high {
+        let mid = (low + high) / 2;
+        if read_checkpoint(e, &checkpoint_key(mid)).ledger <= ledger {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+
+    if low == 0 {
+        0
+    } else {
+        read_checkpoint(e, &checkpoint_key(low - 1)).amount
+    }
+}
+
+pub fn write_balance_checkpoint(e: &Env, addr: Address, amount: i128) {
+    write_checkpoint(
+        e,
+        DataKey::CheckpointCount(addr.clone()),
+        |idx| DataKey::Checkpoint(addr.clone(), idx),
+        amount,
+    );
+}
+
+pub fn write_supply_checkpoint(e: &Env, amount: i128) {
+    write_checkpoint(
+        e,
+        DataKey::SupplyCheckpointCount,
+        DataKey::SupplyCheckpoint,
+        amount,
+    );
+}
+
+pub fn read_balance_at(e: &Env, addr: Address, ledger: u32) -> i128 {
+    read_checkpoint_at(
+        e,
+        DataKey::CheckpointCount(addr.clone()),
+        |idx| DataKey::Checkpoint(addr.clone(), idx),
+        ledger,
+    )
+}
+
+pub fn read_total_supply_at(e: &Env, ledger: u32) -> i128 {
+    read_checkpoint_at(
+        e,
+        DataKey::SupplyCheckpointCount,
+        DataKey::SupplyCheckpoint,
+        ledger,
+    )
+}
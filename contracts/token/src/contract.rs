@@ -0,0 +1,231 @@
+use crate::admin::{has_administrator, read_administrator, write_administrator};
+use crate::allowance::{read_allowance, spend_allowance, write_allowance};
+use crate::authorization::{
+    read_auth_required, read_authorized, write_auth_required, write_authorized,
+};
+use crate::balance::{
+    read_balance, read_total_supply, receive_balance, spend_balance, write_total_supply,
+};
+use crate::checkpoint::{read_balance_at, read_total_supply_at};
+use crate::metadata::{read_decimal, read_name, read_symbol, write_metadata};
+use soroban_sdk::{contract, contractimpl, symbol_short, Address, Env, String};
+
+fn check_nonnegative_amount(amount: i128) {
+    if amount < 0 {
+        panic!("negative amount is not allowed: {}", amount)
+    }
+}
+
+// While authorization is required, only the admin and the addresses it authorized can receive the token
+fn check_authorized_receiver(e: &Env, to: &Address) {
+    if read_auth_required(e) && !read_authorized(e, to.clone()) && *to != read_administrator(e) {
+        panic!("receiver is not authorized");
+    }
+}
+
+fn burn_balance(e: &Env, from: Address, amount: i128) {
+    spend_balance(e, from.clone(), amount);
+    write_total_supply(e, read_total_supply(e) - amount);
+
+    e.events().publish((symbol_short!("burn"), from), amount);
+}
+
+pub trait TokenTrait {
+    // Sets the admin allowed to mint and the token metadata. Can only be called once.
+    fn initialize(e: Env, admin: Address, decimal: u32, name: String, symbol: String);
+
+    // Mints "amount" to "to". Requires the admin's authorization.
+    fn mint(e: Env, to: Address, amount: i128);
+
+    fn set_admin(e: Env, new_admin: Address);
+
+    // Turns on or off the requirement for receivers of mints and transfers to be authorized by the admin.
+    // Holders can always burn what they already have.
+    fn set_auth_required(e: Env, required: bool);
+
+    fn auth_required(e: Env) -> bool;
+
+    // Authorizes "id" to receive the token, or revokes it. Requires the admin's authorization.
+    fn set_authorized(e: Env, id: Address, authorize: bool);
+
+    fn authorized(e: Env, id: Address) -> bool;
+
+    // Returns the amount "spender" may still transfer or burn from "from"
+    fn allowance(e: Env, from: Address, spender: Address) -> i128;
+
+    // Lets "spender" transfer or burn up to "amount" of "from"'s balance until "expiration_ledger"
+    fn approve(e: Env, from: Address, spender: Address, amount: i128, expiration_ledger: u32);
+
+    fn balance(e: Env, id: Address) -> i128;
+
+    fn transfer(e: Env, from: Address, to: Address, amount: i128);
+
+    // Transfers "amount" from "from" to "to", consuming "spender"'s allowance
+    fn transfer_from(e: Env, spender: Address, from: Address, to: Address, amount: i128);
+
+    fn burn(e: Env, from: Address, amount: i128);
+
+    // Burns "amount" of "from"'s balance, consuming "spender"'s allowance
+    fn burn_from(e: Env, spender: Address, from: Address, amount: i128);
+
+    fn total_supply(e: Env) -> i128;
+
+    // Returns the balance of "id" at the end of the past "ledger", for voting on snapshots that
+    // can't be inflated by tokens borrowed within the vote's own ledger
+    fn balance_at(e: Env, id: Address, ledger: u32) -> i128;
+
+    // Returns the total supply at the end of the past "ledger"
+    fn total_supply_at(e: Env, ledger: u32) -> i128;
+
+    fn decimals(e: Env) -> u32;
+
+    fn name(e: Env) -> String;
+
+    fn symbol(e: Env) -> String;
+}
+
+#[contract]
+pub struct Token;
+
+#[contractimpl]
+impl TokenTrait for Token {
+    fn initialize(e: Env, admin: Address, decimal: u32, name: String, symbol: String) {
+        if has_administrator(&e) {
+            panic!("already initialized")
+        }
+        write_administrator(&e, &admin);
+        if decimal > u8::MAX.into() {
+            panic!("Decimal must fit in a u8");
+        }
+
+        write_metadata(&e, decimal, name, symbol);
+        write_total_supply(&e, 0);
+    }
+
+    fn mint(e: Env, to: Address, amount: i128) {
+        check_nonnegative_amount(amount);
+        let admin = read_administrator(&e);
+        admin.require_auth();
+
+        check_authorized_receiver(&e, &to);
+        receive_balance(&e, to.clone(), amount);
+        write_total_supply(&e, read_total_supply(&e) + amount);
+
+        e.events().publish((symbol_short!("mint"), admin, to), amount);
+    }
+
+    fn set_admin(e: Env, new_admin: Address) {
+        let admin = read_administrator(&e);
+        admin.require_auth();
+
+        write_administrator(&e, &new_admin);
+        e.events().publish((symbol_short!("set_admin"), admin), new_admin);
+    }
+
+    fn set_auth_required(e: Env, required: bool) {
+        let admin = read_administrator(&e);
+        admin.require_auth();
+
+        write_auth_required(&e, required);
+    }
+
+    fn auth_required(e: Env) -> bool {
+        read_auth_required(&e)
+    }
+
+    fn set_authorized(e: Env, id: Address, authorize: bool) {
+        let admin = read_administrator(&e);
+        admin.require_auth();
+
+        write_authorized(&e, id.clone(), authorize);
+        e.events().publish((symbol_short!("set_auth"), admin, id), authorize);
+    }
+
+    fn authorized(e: Env, id: Address) -> bool {
+        read_authorized(&e, id)
+    }
+
+    fn allowance(e: Env, from: Address, spender: Address) -> i128 {
+        read_allowance(&e, from, spender).amount
+    }
+
+    fn approve(e: Env, from: Address, spender: Address, amount: i128, expiration_ledger: u32) {
+        from.require_auth();
+
+        check_nonnegative_amount(amount);
+
+        write_allowance(&e, from.clone(), spender.clone(), amount, expiration_ledger);
+        e.events().publish(
+            (symbol_short!("approve"), from, spender),
+            (amount, expiration_ledger),
+        );
+    }
+
+    fn balance(e: Env, id: Address) -> i128 {
+        read_balance(&e, id)
+    }
+
+    fn transfer(e: Env, from: Address, to: Address, amount: i128) {
+        from.require_auth();
+
+        check_nonnegative_amount(amount);
+
+        check_authorized_receiver(&e, &to);
+        spend_balance(&e, from.clone(), amount);
+        receive_balance(&e, to.clone(), amount);
+        e.events().publish((symbol_short!("transfer"), from, to), amount);
+    }
+
+    fn transfer_from(e: Env, spender: Address, from: Address, to: Address, amount: i128) {
+        spender.require_auth();
+
+        check_nonnegative_amount(amount);
+
+        check_authorized_receiver(&e, &to);
+        spend_allowance(&e, from.clone(), spender, amount);
+        spend_balance(&e, from.clone(), amount);
+        receive_balance(&e, to.clone(), amount);
+        e.events().publish((symbol_short!("transfer"), from, to), amount);
+    }
+
+    fn burn(e: Env, from: Address, amount: i128) {
+        from.require_auth();
+
+        check_nonnegative_amount(amount);
+
+        burn_balance(&e, from, amount);
+    }
+
+    fn burn_from(e: Env, spender: Address, from: Address, amount: i128) {
+        spender.require_auth();
+
+        check_nonnegative_amount(amount);
+
+        spend_allowance(&e, from.clone(), spender, amount);
+        burn_balance(&e, from, amount);
+    }
+
+    fn total_supply(e: Env) -> i128 {
+        read_total_supply(&e)
+    }
+
+    fn balance_at(e: Env, id: Address, ledger: u32) -> i128 {
+        read_balance_at(&e, id, ledger)
+    }
+
+    fn total_supply_at(e: Env, ledger: u32) -> i128 {
+        read_total_supply_at(&e, ledger)
+    }
+
+    fn decimals(e: Env) -> u32 {
+        read_decimal(&e)
+    }
+
+    fn name(e: Env) -> String {
+        read_name(&e)
+    }
+
+    fn symbol(e: Env) -> String {
+        read_symbol(&e)
+    }
+}
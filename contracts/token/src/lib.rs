@@ -0,0 +1,13 @@
+#![no_std]
+
+mod admin;
+mod allowance;
+mod authorization;
+mod balance;
+mod checkpoint;
+pub mod contract;
+mod metadata;
+mod storage_types;
+mod test;
+
+pub use crate::contract::{Token, TokenClient};
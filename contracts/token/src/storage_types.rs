@@ -0,0 +1,44 @@
+use soroban_sdk::{contracttype, Address};
+
+pub(crate) const DAY_IN_LEDGERS: u32 = 17280;
+pub(crate) const BALANCE_BUMP_AMOUNT: u32 = 30 * DAY_IN_LEDGERS;
+pub(crate) const BALANCE_LIFETIME_THRESHOLD: u32 = BALANCE_BUMP_AMOUNT - DAY_IN_LEDGERS;
+
+#[derive(Clone)]
+#[contracttype]
+pub struct AllowanceDataKey {
+    pub from: Address,
+    pub spender: Address,
+}
+
+#[contracttype]
+pub struct AllowanceValue {
+    pub amount: i128,
+    pub expiration_ledger: u32,
+}
+
+// Value of a balance or the total supply at the end of "ledger"
+#[derive(Clone)]
+#[contracttype]
+pub struct Checkpoint {
+    pub ledger: u32,
+    pub amount: i128,
+}
+
+#[derive(Clone)]
+#[contracttype]
+pub enum DataKey {
+    Allowance(AllowanceDataKey),
+    Balance(Address),
+    Admin,
+    TotalSupply,
+    Decimal,
+    Name,
+    Symbol,
+    Checkpoint(Address, u32),
+    CheckpointCount(Address),
+    SupplyCheckpoint(u32),
+    SupplyCheckpointCount,
+    AuthRequired,
+    Authorized(Address),
+}
@@ -0,0 +1,310 @@
+#![cfg(test)]
+extern crate std;
+
+use crate::{storage_types::DataKey, Token, TokenClient};
+use soroban_sdk::{
+    symbol_short,
+    testutils::{Address as _, AuthorizedFunction, AuthorizedInvocation, Ledger},
+    Address, Env, IntoVal, String,
+};
+
+fn create_token<'a>(e: &Env, admin: &Address) -> TokenClient<'a> {
+    let token = TokenClient::new(e, &e.register_contract(None, Token {}));
+    token.initialize(
+        admin,
+        &7,
+        &String::from_slice(e, "name"),
+        &String::from_slice(e, "symbol"),
+    );
+    token
+}
+
+#[test]
+fn test() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin1 = Address::random(&e);
+    let admin2 = Address::random(&e);
+    let user1 = Address::random(&e);
+    let user2 = Address::random(&e);
+    let user3 = Address::random(&e);
+    let token = create_token(&e, &admin1);
+
+    token.mint(&user1, &1000);
+    assert_eq!(
+        e.auths(),
+        std::vec![(
+            admin1.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    token.address.clone(),
+                    symbol_short!("mint"),
+                    (&user1, 1000_i128).into_val(&e),
+                )),
+                sub_invocations: std::vec![]
+            }
+        )]
+    );
+    assert_eq!(token.balance(&user1), 1000);
+    assert_eq!(token.total_supply(), 1000);
+
+    token.approve(&user2, &user3, &500, &200);
+    assert_eq!(token.allowance(&user2, &user3), 500);
+
+    token.transfer(&user1, &user2, &600);
+    assert_eq!(
+        e.auths(),
+        std::vec![(
+            user1.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    token.address.clone(),
+                    symbol_short!("transfer"),
+                    (&user1, &user2, 600_i128).into_val(&e),
+                )),
+                sub_invocations: std::vec![]
+            }
+        )]
+    );
+    assert_eq!(token.balance(&user1), 400);
+    assert_eq!(token.balance(&user2), 600);
+
+    token.transfer_from(&user3, &user2, &user1, &400);
+    assert_eq!(token.balance(&user1), 800);
+    assert_eq!(token.balance(&user2), 200);
+    assert_eq!(token.allowance(&user2, &user3), 100);
+
+    token.set_admin(&admin2);
+    token.mint(&user3, &100);
+    assert_eq!(
+        e.auths(),
+        std::vec![(
+            admin2.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    token.address.clone(),
+                    symbol_short!("mint"),
+                    (&user3, 100_i128).into_val(&e),
+                )),
+                sub_invocations: std::vec![]
+            }
+        )]
+    );
+    assert_eq!(token.balance(&user3), 100);
+    assert_eq!(token.total_supply(), 1100);
+}
+
+#[test]
+fn test_burn() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let user1 = Address::random(&e);
+    let user2 = Address::random(&e);
+    let token = create_token(&e, &admin);
+
+    token.mint(&user1, &1000);
+
+    token.approve(&user1, &user2, &500, &200);
+    token.burn_from(&user2, &user1, &500);
+    assert_eq!(
+        e.auths(),
+        std::vec![(
+            user2.clone(),
+            AuthorizedInvocation {
+                function: AuthorizedFunction::Contract((
+                    token.address.clone(),
+                    symbol_short!("burn_from"),
+                    (&user2, &user1, 500_i128).into_val(&e),
+                )),
+                sub_invocations: std::vec![]
+            }
+        )]
+    );
+    assert_eq!(token.allowance(&user1, &user2), 0);
+    assert_eq!(token.balance(&user1), 500);
+
+    token.burn(&user1, &500);
+    assert_eq!(token.balance(&user1), 0);
+    assert_eq!(token.total_supply(), 0);
+}
+
+#[test]
+#[should_panic(expected = "insufficient balance")]
+fn transfer_insufficient_balance() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let user1 = Address::random(&e);
+    let user2 = Address::random(&e);
+    let token = create_token(&e, &admin);
+
+    token.mint(&user1, &1000);
+    token.transfer(&user1, &user2, &1001);
+}
+
+#[test]
+#[should_panic(expected = "insufficient allowance")]
+fn burn_from_insufficient_allowance() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let user1 = Address::random(&e);
+    let user2 = Address::random(&e);
+    let token = create_token(&e, &admin);
+
+    token.mint(&user1, &1000);
+    token.approve(&user1, &user2, &100, &200);
+    token.burn_from(&user2, &user1, &101);
+}
+
+#[test]
+#[should_panic(expected = "already initialized")]
+fn initialize_already_initialized() {
+    let e = Env::default();
+    let admin = Address::random(&e);
+    let token = create_token(&e, &admin);
+
+    token.initialize(
+        &admin,
+        &10,
+        &String::from_slice(&e, "name"),
+        &String::from_slice(&e, "symbol"),
+    );
+}
+
+#[test]
+fn test_metadata() {
+    let e = Env::default();
+    let admin = Address::random(&e);
+    let token = create_token(&e, &admin);
+
+    assert_eq!(token.decimals(), 7);
+    assert_eq!(token.name(), String::from_slice(&e, "name"));
+    assert_eq!(token.symbol(), String::from_slice(&e, "symbol"));
+}
+
+#[test]
+fn test_checkpoints() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let user1 = Address::random(&e);
+    let user2 = Address::random(&e);
+    let token = create_token(&e, &admin);
+
+    e.ledger().with_mut(|l| l.sequence_number = 10);
+    token.mint(&user1, &1000);
+
+    e.ledger().with_mut(|l| l.sequence_number = 20);
+    token.transfer(&user1, &user2, &400);
+    token.mint(&user2, &100);
+
+    e.ledger().with_mut(|l| l.sequence_number = 30);
+    token.burn(&user2, &500);
+
+    e.ledger().with_mut(|l| l.sequence_number = 40);
+    assert_eq!(token.balance_at(&user1, &9), 0);
+    assert_eq!(token.balance_at(&user1, &10), 1000);
+    assert_eq!(token.balance_at(&user1, &25), 600);
+    assert_eq!(token.balance_at(&user2, &20), 500);
+    assert_eq!(token.balance_at(&user2, &30), 0);
+
+    assert_eq!(token.total_supply_at(&15), 1000);
+    assert_eq!(token.total_supply_at(&20), 1100);
+    assert_eq!(token.total_supply_at(&39), 600);
+}
+
+#[test]
+fn test_one_checkpoint_per_ledger() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let user1 = Address::random(&e);
+    let user2 = Address::random(&e);
+    let token = create_token(&e, &admin);
+
+    for ledger in 1..=20 {
+        e.ledger().with_mut(|l| l.sequence_number = ledger * 10);
+        token.mint(&user1, &100);
+        token.transfer(&user1, &user2, &10);
+        token.transfer(&user1, &user2, &20);
+        token.transfer(&user2, &user1, &5);
+    }
+
+    let count = |key: DataKey| -> u32 {
+        e.as_contract(&token.address, || {
+            e.storage().persistent().get(&key).unwrap()
+        })
+    };
+    assert_eq!(count(DataKey::CheckpointCount(user1.clone())), 20);
+    assert_eq!(count(DataKey::CheckpointCount(user2.clone())), 20);
+    // Plus the zero supply written by initialize
+    assert_eq!(count(DataKey::SupplyCheckpointCount), 21);
+
+    e.ledger().with_mut(|l| l.sequence_number = 300);
+    assert_eq!(token.balance_at(&user1, &5), 0);
+    assert_eq!(token.balance_at(&user1, &10), 75);
+    assert_eq!(token.balance_at(&user1, &129), 12 * 75);
+    assert_eq!(token.balance_at(&user2, &130), 13 * 25);
+    assert_eq!(token.balance_at(&user2, &299), 20 * 25);
+    assert_eq!(token.total_supply_at(&75), 700);
+}
+
+#[test]
+#[should_panic(expected = "ledger is not finalized yet")]
+fn balance_at_current_ledger() {
+    let e = Env::default();
+    let admin = Address::random(&e);
+    let user = Address::random(&e);
+    let token = create_token(&e, &admin);
+
+    token.balance_at(&user, &e.ledger().sequence());
+}
+
+#[test]
+fn test_auth_required() {
+    let e = Env::default();
+    e.mock_all_auths();
+
+    let admin = Address::random(&e);
+    let user1 = Address::random(&e);
+    let user2 = Address::random(&e);
+    let token = create_token(&e, &admin);
+
+    token.mint(&user1, &1000);
+    token.set_auth_required(&true);
+    token.set_authorized(&user1, &true);
+    assert!(token.auth_required());
+    assert!(token.authorized(&user1));
+    assert!(!token.authorized(&user2));
+
+    // Only authorized receivers and the admin can be minted or transferred to
+    assert!(token.try_mint(&user2, &100).is_err());
+    assert!(token.try_transfer(&user1, &user2, &100).is_err());
+    token.approve(&user1, &user2, &500, &200);
+    assert!(token.try_transfer_from(&user2, &user1, &user2, &100).is_err());
+    token.transfer_from(&user2, &user1, &admin, &100);
+    token.mint(&user1, &100);
+    assert_eq!(token.balance(&user1), 1000);
+
+    token.set_authorized(&user2, &true);
+    token.transfer(&user1, &user2, &100);
+    assert_eq!(token.balance(&user2), 100);
+
+    // Revoked holders keep what they have and can still burn it
+    token.set_authorized(&user2, &false);
+    assert!(token.try_transfer(&user1, &user2, &100).is_err());
+    token.burn(&user2, &100);
+    assert_eq!(token.balance(&user2), 0);
+
+    token.set_auth_required(&false);
+    token.transfer(&user1, &user2, &100);
+    assert_eq!(token.balance(&user2), 100);
+}